@@ -0,0 +1,539 @@
+// an experimental backend that reads digitiser reports straight from a HID device, for tablets neither RealTimeStylus
+// nor WinTab handle well
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use windows::core::{Error, HSTRING, PCSTR};
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HidD_FreePreparsedData, HidD_GetPreparsedData, HidP_GetCaps, HidP_GetUsageValue,
+    HidP_GetUsages, HidP_GetValueCaps, HidP_Input, HIDP_CAPS, HIDP_VALUE_CAPS,
+};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, ReadFile, FILE_ATTRIBUTE_NORMAL, FILE_GENERIC_READ, FILE_SHARE_READ,
+    FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+
+use crate::win32::{InputSource, StylusSample, WinTabEvent, ERROR_FN};
+use crate::{tilt_to_spherical, EasyTabResult};
+
+// usage pages and usages, from the HID usage tables
+const PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const PAGE_GENERIC_DEVICE: u16 = 0x06;
+const PAGE_DIGITIZER: u16 = 0x0d;
+const USAGE_X: u16 = 0x30;
+const USAGE_Y: u16 = 0x31;
+const USAGE_TIP_PRESSURE: u16 = 0x30;
+const USAGE_X_TILT: u16 = 0x3d;
+const USAGE_Y_TILT: u16 = 0x3e;
+const USAGE_TIP_SWITCH: u16 = 0x42;
+const USAGE_BATTERY_STRENGTH: u16 = 0x20;
+
+// the most buttons read from a single report
+const MAX_USAGES: usize = 32;
+
+// the radians (si rotation) unit of the HID unit system. angles are otherwise in degrees (english rotation).
+const UNIT_RADIANS: u32 = 0x12;
+
+// the exponent in value caps is a signed nibble
+fn unit_exponent(units_exp: u32) -> i32 {
+    let exponent = (units_exp & 0xf) as i32;
+
+    if exponent > 7 {
+        exponent - 16
+    } else {
+        exponent
+    }
+}
+
+// how a value in the input reports is encoded, from its value caps
+#[derive(Clone, Copy)]
+struct ValueRange {
+    logical_min: i32,
+    logical_max: i32,
+    physical_min: i32,
+    physical_max: i32,
+    bits: u16,
+    units: u32,
+    // the power of ten the physical value is scaled by
+    exponent: i32,
+}
+
+impl ValueRange {
+    // reads the encoding of a value from its value caps
+    fn from_caps(caps: &HIDP_VALUE_CAPS) -> Self {
+        Self {
+            logical_min: caps.LogicalMin,
+            logical_max: caps.LogicalMax,
+            physical_min: caps.PhysicalMin,
+            physical_max: caps.PhysicalMax,
+            bits: caps.BitSize,
+            units: caps.Units,
+            exponent: unit_exponent(caps.UnitsExp),
+        }
+    }
+
+    // interprets the raw bits of a value. values are returned as raw bits, so signed ones have to be sign extended.
+    fn signed(&self, raw: u32) -> i32 {
+        if self.logical_min < 0 && self.bits > 0 && self.bits < 32 {
+            let shift = 32 - self.bits as u32;
+            ((raw << shift) as i32) >> shift
+        } else {
+            raw as i32
+        }
+    }
+
+    // scales a value to between 0 and 1 over the logical range, or `None` if the range is empty
+    fn normalize(&self, value: i32) -> Option<f32> {
+        (self.logical_max > self.logical_min).then(|| {
+            (value - self.logical_min) as f32 / (self.logical_max - self.logical_min) as f32
+        })
+    }
+
+    // converts a battery strength into a percentage, or `None` if the range is empty
+    fn percent(&self, value: i32) -> Option<u8> {
+        let (min, max) = (self.logical_min, self.logical_max);
+
+        (max > min).then(|| ((value - min) as i64 * 100 / (max - min) as i64).clamp(0, 100) as u8)
+    }
+
+    // converts a raw value into an angle in degrees, using the physical range and unit from the descriptor. a value
+    // without a physical range is assumed to already be in degrees.
+    fn degrees(&self, value: i32) -> f32 {
+        let logical = (self.logical_max - self.logical_min) as f32;
+        let physical = (self.physical_max - self.physical_min) as f32;
+
+        if logical == 0.0 || physical == 0.0 {
+            return value as f32;
+        }
+
+        let value =
+            self.physical_min as f32 + (value - self.logical_min) as f32 * physical / logical;
+        let value = value * 10f32.powi(self.exponent);
+
+        match self.units {
+            UNIT_RADIANS => value.to_degrees(),
+            // degrees, or a unit that doesn't make sense for an angle
+            _ => value,
+        }
+    }
+}
+
+/// **Experimental**: a tablet read straight from its HID digitiser reports, for hardware the other backends don't
+/// handle well.
+///
+/// The device's report descriptor (as parsed by the HID class driver) is used to find the digitiser usages: X, Y, tip
+/// pressure, tilt and the tip switch. Reports are read with [`next_event`](Self::next_event), which blocks, so this is
+/// best used from a dedicated thread.
+pub struct HidTablet {
+    handle: HANDLE,
+    // the parsed report descriptor
+    preparsed: isize,
+    report_len: usize,
+    // the encoding of every value in the input reports, keyed by usage page and usage
+    ranges: HashMap<(u16, u16), ValueRange>,
+
+    down: Cell<bool>,
+    // the battery level from the most recent report that had one, as a percentage
+    battery: Cell<Option<u8>>,
+    // events decoded from a report that haven't been returned yet
+    pending: RefCell<VecDeque<WinTabEvent>>,
+}
+
+impl HidTablet {
+    /// Opens the HID device at the given path (e.g. one returned by `SetupDiGetDeviceInterfaceDetailW` for the HID
+    /// interface class).
+    pub fn open(path: &str) -> EasyTabResult<Self> {
+        let handle = unsafe {
+            CreateFileW(
+                &HSTRING::from(path),
+                FILE_GENERIC_READ,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null(),
+                OPEN_EXISTING,
+                FILE_ATTRIBUTE_NORMAL,
+                HANDLE::default(),
+            )
+            .map_err(ERROR_FN)?
+        };
+
+        let mut preparsed = 0;
+
+        if unsafe { HidD_GetPreparsedData(handle, &mut preparsed) }.0 == 0 {
+            let e = Error::from_win32();
+            unsafe { CloseHandle(handle) };
+
+            return Err(ERROR_FN(e));
+        }
+
+        // the handles are owned from here on, so an error cleans them up
+        let mut slf = Self {
+            handle,
+            preparsed,
+            report_len: 0,
+            ranges: HashMap::new(),
+            down: Cell::default(),
+            battery: Cell::default(),
+            pending: RefCell::default(),
+        };
+
+        let mut caps = HIDP_CAPS::default();
+        unsafe { HidP_GetCaps(preparsed, &mut caps).map_err(ERROR_FN)? };
+
+        let mut count = caps.NumberInputValueCaps;
+        let mut values = vec![HIDP_VALUE_CAPS::default(); count as usize];
+
+        if count > 0 {
+            unsafe {
+                HidP_GetValueCaps(HidP_Input, values.as_mut_ptr(), &mut count, preparsed)
+                    .map_err(ERROR_FN)?
+            };
+        }
+
+        slf.report_len = caps.InputReportByteLength as usize;
+        slf.ranges = values[..count as usize]
+            .iter()
+            .filter(|v| v.IsRange.0 == 0)
+            .map(|v| {
+                let usage = unsafe { v.Anonymous.NotRange.Usage };
+
+                ((v.UsagePage, usage), ValueRange::from_caps(v))
+            })
+            .collect();
+
+        Ok(slf)
+    }
+
+    /// Waits for the next event from the device.
+    ///
+    /// Each report with a position is decoded into a [`WinTabEvent::StylusMove`] while the tip is down, or a
+    /// [`WinTabEvent::HoverMove`] otherwise, preceded by [`WinTabEvent::StylusActive`] or
+    /// [`WinTabEvent::StylusInactive`] when the tip goes down or lifts. Reports without one (e.g. a separate battery
+    /// report) don't produce events.
+    pub fn next_event(&self) -> EasyTabResult<WinTabEvent> {
+        loop {
+            if let Some(event) = self.pending.borrow_mut().pop_front() {
+                return Ok(event);
+            }
+
+            let report = self.read_report()?;
+            self.handle_report(&report);
+        }
+    }
+
+    /// Returns the charge left in the pen's battery as a percentage, or `None` if the pen doesn't report it (or no
+    /// report with it has arrived yet).
+    ///
+    /// Only wireless pens that report a battery strength in their digitiser reports support this. Neither
+    /// RealTimeStylus nor WinTab expose the battery, so it is only available through this backend.
+    pub fn pen_battery(&self) -> Option<u8> {
+        self.battery.get()
+    }
+
+    // blocks until the device sends an input report
+    fn read_report(&self) -> EasyTabResult<Vec<u8>> {
+        let mut report = vec![0u8; self.report_len];
+        let mut read = 0;
+
+        let ok = unsafe {
+            ReadFile(
+                self.handle,
+                report.as_mut_ptr() as *mut _,
+                report.len() as u32,
+                &mut read,
+                std::ptr::null_mut(),
+            )
+        };
+
+        if !ok.as_bool() {
+            return Err(ERROR_FN(Error::from_win32()));
+        }
+
+        report.truncate(read as usize);
+
+        Ok(report)
+    }
+
+    // decodes a report, queueing the events for it
+    fn handle_report(&self, report: &[u8]) {
+        let value = |page, usage| self.value(report, page, usage);
+
+        if let Some(percent) = battery(&self.ranges, value) {
+            self.battery.set(Some(percent));
+        }
+
+        // devices can send other reports (e.g. for the battery) that don't carry the stylus' state at all
+        let sample = match decode(&self.ranges, value) {
+            Some(sample) => StylusSample {
+                instant: Some(Instant::now()),
+                ..sample
+            },
+            None => return,
+        };
+        let down = self.tip_down(report) || sample.pressure > 0.0;
+
+        let mut pending = self.pending.borrow_mut();
+
+        if down != self.down.replace(down) {
+            pending.push_back(if down {
+                WinTabEvent::StylusActive
+            } else {
+                WinTabEvent::StylusInactive
+            });
+        }
+
+        pending.push_back(if down {
+            WinTabEvent::StylusMove(sample)
+        } else {
+            WinTabEvent::HoverMove(sample)
+        });
+    }
+
+    // reads a value from a report, or `None` if the device doesn't report it
+    fn value(&self, report: &[u8], page: u16, usage: u16) -> Option<i32> {
+        let range = self.ranges.get(&(page, usage))?;
+        let mut value = 0u32;
+
+        unsafe {
+            HidP_GetUsageValue(
+                HidP_Input,
+                page,
+                0,
+                usage,
+                &mut value,
+                self.preparsed,
+                PCSTR(report.as_ptr()),
+                report.len() as u32,
+            )
+            .ok()?
+        };
+
+        Some(range.signed(value))
+    }
+
+    // whether the tip switch is pressed in a report
+    fn tip_down(&self, report: &[u8]) -> bool {
+        let mut usages = [0u16; MAX_USAGES];
+        let mut count = MAX_USAGES as u32;
+
+        // the report isn't written to, the signature just isn't const
+        let ok = unsafe {
+            HidP_GetUsages(
+                HidP_Input,
+                PAGE_DIGITIZER,
+                0,
+                usages.as_mut_ptr(),
+                &mut count,
+                self.preparsed,
+                windows::core::PSTR(report.as_ptr() as *mut u8),
+                report.len() as u32,
+            )
+        };
+
+        ok.is_ok() && usages[..count as usize].contains(&USAGE_TIP_SWITCH)
+    }
+}
+
+// the pen's battery level in a report as a percentage, given a way to read the report's values
+fn battery(
+    ranges: &HashMap<(u16, u16), ValueRange>,
+    value: impl Fn(u16, u16) -> Option<i32>,
+) -> Option<u8> {
+    let range = ranges.get(&(PAGE_GENERIC_DEVICE, USAGE_BATTERY_STRENGTH))?;
+
+    range.percent(value(PAGE_GENERIC_DEVICE, USAGE_BATTERY_STRENGTH)?)
+}
+
+// decodes a single report given a way to read its values, or `None` if it doesn't have a position
+fn decode(
+    ranges: &HashMap<(u16, u16), ValueRange>,
+    value: impl Fn(u16, u16) -> Option<i32>,
+) -> Option<StylusSample> {
+    let x = value(PAGE_GENERIC_DESKTOP, USAGE_X)?;
+    let y = value(PAGE_GENERIC_DESKTOP, USAGE_Y)?;
+
+    let pressure = ranges
+        .get(&(PAGE_DIGITIZER, USAGE_TIP_PRESSURE))
+        .zip(value(PAGE_DIGITIZER, USAGE_TIP_PRESSURE))
+        .and_then(|(range, p)| range.normalize(p))
+        .unwrap_or(0.0);
+
+    let tilt = |usage| {
+        let range = ranges.get(&(PAGE_DIGITIZER, usage))?;
+
+        Some(range.degrees(value(PAGE_DIGITIZER, usage)?))
+    };
+    let tilt = tilt(USAGE_X_TILT).zip(tilt(USAGE_Y_TILT));
+
+    let (azimuth, altitude) = tilt.map(|(x, y)| tilt_to_spherical(x, y)).unzip();
+
+    Some(StylusSample {
+        x,
+        y,
+        z: None,
+        pressure,
+        tilt,
+        azimuth,
+        altitude,
+        pitch: None,
+        roll: None,
+        twist: None,
+        yaw: None,
+        serial: None,
+        status: None,
+        contact_size: None,
+        cursor: 0,
+        input_source: InputSource::Pen,
+        instant: None,
+        os_time: None,
+    })
+}
+
+impl Drop for HidTablet {
+    fn drop(&mut self) {
+        unsafe {
+            HidD_FreePreparsedData(self.preparsed);
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(logical: (i32, i32), physical: (i32, i32), bits: u16) -> ValueRange {
+        ValueRange {
+            logical_min: logical.0,
+            logical_max: logical.1,
+            physical_min: physical.0,
+            physical_max: physical.1,
+            bits,
+            units: 0x14,
+            exponent: 0,
+        }
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn unit_exponents_are_signed_nibbles() {
+        assert_eq!(unit_exponent(0x0), 0);
+        assert_eq!(unit_exponent(0x2), 2);
+        assert_eq!(unit_exponent(0x7), 7);
+        assert_eq!(unit_exponent(0x8), -8);
+        assert_eq!(unit_exponent(0xe), -2);
+        assert_eq!(unit_exponent(0xf), -1);
+        // only the low nibble is used
+        assert_eq!(unit_exponent(0xf2), 2);
+    }
+
+    #[test]
+    fn signed_values_are_sign_extended() {
+        let signed = range((-64, 63), (0, 0), 7);
+
+        assert_eq!(signed.signed(0x3f), 63);
+        assert_eq!(signed.signed(0x40), -64);
+        assert_eq!(signed.signed(0x7f), -1);
+
+        // unsigned values are left alone
+        assert_eq!(range((0, 127), (0, 0), 7).signed(0x7f), 127);
+        assert_eq!(range((-1, 0), (0, 0), 32).signed(u32::MAX), -1);
+    }
+
+    #[test]
+    fn tilt_is_converted_to_degrees() {
+        // a 0.01 degree resolution
+        let mut tilt = range((-9000, 9000), (-9000, 9000), 16);
+        tilt.exponent = -2;
+
+        assert_near(tilt.degrees(-9000), -90.0);
+        assert_near(tilt.degrees(4500), 45.0);
+
+        // a logical range that doesn't match the physical one
+        let tilt = range((-64, 63), (-60, 60), 7);
+
+        assert_near(tilt.degrees(-64), -60.0);
+        assert_near(tilt.degrees(63), 60.0);
+
+        // half a radian
+        let mut tilt = range((-1000, 1000), (-1000, 1000), 16);
+        tilt.units = UNIT_RADIANS;
+        tilt.exponent = -3;
+
+        assert_near(tilt.degrees(500), 0.5f32.to_degrees());
+
+        // without a physical range the value is already in degrees
+        assert_near(range((-60, 60), (0, 0), 8).degrees(30), 30.0);
+    }
+
+    #[test]
+    fn pressure_and_battery_are_normalised() {
+        assert_near(range((0, 8191), (0, 0), 13).normalize(8191).unwrap(), 1.0);
+        assert_near(range((-100, 100), (0, 0), 8).normalize(0).unwrap(), 0.5);
+        assert_eq!(range((0, 0), (0, 0), 8).normalize(0), None);
+
+        let battery = range((0, 4), (0, 0), 3);
+
+        assert_eq!(battery.percent(3), Some(75));
+        assert_eq!(battery.percent(5), Some(100));
+        assert_eq!(battery.percent(-1), Some(0));
+        assert_eq!(range((1, 1), (0, 0), 8).percent(1), None);
+    }
+
+    #[test]
+    fn decodes_a_report() {
+        let tilt = range((-60, 60), (-60, 60), 8);
+
+        let ranges = HashMap::from([
+            (
+                (PAGE_GENERIC_DESKTOP, USAGE_X),
+                range((0, 32767), (0, 0), 16),
+            ),
+            (
+                (PAGE_GENERIC_DESKTOP, USAGE_Y),
+                range((0, 32767), (0, 0), 16),
+            ),
+            (
+                (PAGE_DIGITIZER, USAGE_TIP_PRESSURE),
+                range((0, 4095), (0, 0), 12),
+            ),
+            ((PAGE_DIGITIZER, USAGE_X_TILT), tilt),
+            ((PAGE_DIGITIZER, USAGE_Y_TILT), tilt),
+            (
+                (PAGE_GENERIC_DEVICE, USAGE_BATTERY_STRENGTH),
+                range((0, 100), (0, 0), 8),
+            ),
+        ]);
+        let report = HashMap::from([
+            ((PAGE_GENERIC_DESKTOP, USAGE_X), 1000),
+            ((PAGE_GENERIC_DESKTOP, USAGE_Y), 2000),
+            ((PAGE_DIGITIZER, USAGE_TIP_PRESSURE), 4095),
+            ((PAGE_DIGITIZER, USAGE_X_TILT), 0),
+            ((PAGE_DIGITIZER, USAGE_Y_TILT), -30),
+            ((PAGE_GENERIC_DEVICE, USAGE_BATTERY_STRENGTH), 42),
+        ]);
+        let value = |page, usage| report.get(&(page, usage)).copied();
+
+        let sample = decode(&ranges, value).unwrap();
+
+        assert_eq!((sample.x, sample.y), (1000, 2000));
+        assert_near(sample.pressure, 1.0);
+        assert_eq!(sample.tilt, Some((0.0, -30.0)));
+        assert_near(sample.azimuth.unwrap(), 270.0);
+        assert_near(sample.altitude.unwrap(), 60.0);
+        assert_eq!(battery(&ranges, value), Some(42));
+
+        // a report without a position, e.g. a separate battery report
+        let battery_only = |page, usage| {
+            (page == PAGE_GENERIC_DEVICE && usage == USAGE_BATTERY_STRENGTH).then_some(42)
+        };
+
+        assert!(decode(&ranges, battery_only).is_none());
+        assert_eq!(battery(&ranges, battery_only), Some(42));
+    }
+}
@@ -0,0 +1,321 @@
+// serves the events as json over a named pipe, for tools watching the tablet from another process
+
+use std::os::windows::io::AsRawHandle;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use windows::core::{Error, HSTRING};
+use windows::Win32::Foundation::{CloseHandle, ERROR_PIPE_CONNECTED, HANDLE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, WriteFile, FILE_ATTRIBUTE_NORMAL, FILE_GENERIC_READ, FILE_SHARE_NONE,
+    OPEN_EXISTING, PIPE_ACCESS_OUTBOUND,
+};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_TYPE_BYTE, PIPE_WAIT,
+};
+use windows::Win32::System::IO::CancelSynchronousIo;
+
+use crate::win32::{WinTabEvent, ERROR_FN};
+use crate::EasyTabResult;
+
+// the size of the pipe's outgoing buffer. a client that stops reading blocks the pipe once it fills up.
+const BUFFER_SIZE: u32 = 64 * 1024;
+
+// the number of events waiting to be written before new ones are dropped, e.g. while no client is connected
+const BACKLOG: usize = 1024;
+
+// how long dropping the pipe waits for the thread to finish, before leaving it to finish on its own
+const STOP_TIMEOUT: Duration = Duration::from_millis(500);
+
+// how long to wait after failing to wait for a client, doubled with every failure in a row up to the maximum, so a
+// broken pipe doesn't spin
+const CONNECT_BACKOFF: Duration = Duration::from_millis(1);
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+// a named pipe, served on its own thread so a slow (or missing) client doesn't hold up the tablet
+pub(crate) struct EventPipe {
+    path: HSTRING,
+    sender: Option<SyncSender<String>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventPipe {
+    // creates the pipe `\\.\pipe\<name>`, ready for a client to connect to
+    pub(crate) fn create(name: &str) -> EasyTabResult<Self> {
+        let path = HSTRING::from(format!(r"\\.\pipe\{}", name));
+
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                &path,
+                PIPE_ACCESS_OUTBOUND,
+                PIPE_TYPE_BYTE | PIPE_WAIT,
+                1,
+                BUFFER_SIZE,
+                0,
+                0,
+                std::ptr::null(),
+            )
+        };
+
+        if pipe.is_invalid() {
+            return Err(ERROR_FN(Error::from_win32()));
+        }
+
+        let (sender, receiver) = sync_channel(BACKLOG);
+        let thread = std::thread::spawn(move || serve(pipe, receiver));
+
+        Ok(Self {
+            path,
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    // a sender for the events, each of which is written to the pipe as a line of json. sending should use `try_send`,
+    // dropping the event when the backlog is full rather than blocking the tablet.
+    pub(crate) fn sender(&self) -> SyncSender<String> {
+        self.sender.clone().unwrap()
+    }
+
+    // connects to the pipe and disconnects straight away, to wake up a thread waiting for a client. with a client
+    // already connected this fails, but the thread isn't waiting for one then.
+    fn wake(&self) {
+        let wake = unsafe {
+            CreateFileW(
+                &self.path,
+                FILE_GENERIC_READ,
+                FILE_SHARE_NONE,
+                std::ptr::null(),
+                OPEN_EXISTING,
+                FILE_ATTRIBUTE_NORMAL,
+                HANDLE::default(),
+            )
+        };
+
+        if let Ok(handle) = wake {
+            unsafe { CloseHandle(handle) };
+        }
+    }
+}
+
+impl Drop for EventPipe {
+    // closes the pipe and waits for the thread, so a new pipe with the same name can be created straight away
+    fn drop(&mut self) {
+        drop(self.sender.take());
+
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+
+        let deadline = Instant::now() + STOP_TIMEOUT;
+
+        // the thread stops once it sees the channel has closed, but it may be blocked waiting for a client, or writing
+        // to one that stopped reading. it's woken until it stops, as it can block again between two wake ups.
+        while !thread.is_finished() {
+            if Instant::now() >= deadline {
+                return;
+            }
+
+            unsafe { CancelSynchronousIo(HANDLE(thread.as_raw_handle() as isize)) };
+            self.wake();
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let _ = thread.join();
+    }
+}
+
+// serialises an event into a line for the pipe
+pub(crate) fn to_line(event: &WinTabEvent) -> Option<String> {
+    serde_json::to_string(event).ok().map(|json| json + "\n")
+}
+
+// how long to wait before waiting for a client again, after the given number of failures in a row
+fn connect_backoff(failures: u32) -> Duration {
+    CONNECT_BACKOFF
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_CONNECT_BACKOFF)
+}
+
+// writes the events to one client at a time until the pipe is dropped
+fn serve(pipe: HANDLE, receiver: Receiver<String>) {
+    let mut failures = 0;
+
+    'clients: loop {
+        // the pipe may have been dropped while the last client was being served
+        if let Err(TryRecvError::Disconnected) = receiver.try_recv() {
+            break;
+        }
+
+        // fails with `ERROR_PIPE_CONNECTED` if a client connected before the call, which is fine
+        let connected = unsafe { ConnectNamedPipe(pipe, std::ptr::null_mut()) }.as_bool()
+            || Error::from_win32().code() == ERROR_PIPE_CONNECTED.to_hresult();
+
+        // events from while nobody was connected are stale
+        loop {
+            match receiver.try_recv() {
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'clients,
+            }
+        }
+
+        if connected {
+            failures = 0;
+
+            loop {
+                let line = match receiver.recv() {
+                    Ok(line) => line,
+                    Err(_) => break 'clients,
+                };
+
+                if !write_all(pipe, line.as_bytes()) {
+                    // the client went away
+                    break;
+                }
+            }
+        } else {
+            std::thread::sleep(connect_backoff(failures));
+            failures = failures.saturating_add(1);
+        }
+
+        unsafe { DisconnectNamedPipe(pipe) };
+    }
+
+    unsafe {
+        DisconnectNamedPipe(pipe);
+        CloseHandle(pipe);
+    }
+}
+
+fn write_all(pipe: HANDLE, mut bytes: &[u8]) -> bool {
+    while !bytes.is_empty() {
+        let mut written = 0;
+
+        let ok = unsafe {
+            WriteFile(
+                pipe,
+                bytes.as_ptr() as *const _,
+                bytes.len() as u32,
+                &mut written,
+                std::ptr::null_mut(),
+            )
+        };
+
+        if !ok.as_bool() || written == 0 {
+            return false;
+        }
+
+        bytes = &bytes[written as usize..];
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use windows::Win32::Storage::FileSystem::ReadFile;
+
+    use super::*;
+    use crate::win32::StylusSample;
+
+    fn event() -> WinTabEvent {
+        WinTabEvent::StylusMove(StylusSample {
+            x: 120,
+            y: -40,
+            pressure: 0.5,
+            tilt: Some((10.0, -20.0)),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let line = to_line(&event()).unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+
+        match serde_json::from_str(&line).unwrap() {
+            WinTabEvent::StylusMove(sample) => match event() {
+                WinTabEvent::StylusMove(expected) => assert_eq!(sample, expected),
+                _ => unreachable!(),
+            },
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn connect_backoff_doubles_up_to_the_maximum() {
+        assert_eq!(connect_backoff(0), CONNECT_BACKOFF);
+        assert_eq!(connect_backoff(1), CONNECT_BACKOFF * 2);
+        assert_eq!(connect_backoff(3), CONNECT_BACKOFF * 8);
+        assert_eq!(connect_backoff(20), MAX_CONNECT_BACKOFF);
+        assert_eq!(connect_backoff(u32::MAX), MAX_CONNECT_BACKOFF);
+    }
+
+    #[test]
+    fn clients_read_lines() {
+        let name = format!("easytab-test-{}", std::process::id());
+        let pipe = EventPipe::create(&name).unwrap();
+
+        let client = unsafe {
+            CreateFileW(
+                &HSTRING::from(format!(r"\\.\pipe\{}", name)),
+                FILE_GENERIC_READ,
+                FILE_SHARE_NONE,
+                std::ptr::null(),
+                OPEN_EXISTING,
+                FILE_ATTRIBUTE_NORMAL,
+                HANDLE::default(),
+            )
+            .unwrap()
+        };
+
+        // events sent before the thread sees the client are dropped as stale, so keep sending until one is read
+        let done = Arc::new(AtomicBool::new(false));
+        let sender = pipe.sender();
+        let sending = {
+            let done = done.clone();
+
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let _ = sender.try_send(to_line(&event()).unwrap());
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            })
+        };
+
+        let expected = to_line(&event()).unwrap();
+        let mut line = vec![0u8; expected.len()];
+        let mut read = 0;
+
+        while read < line.len() {
+            let mut n = 0;
+            let ok = unsafe {
+                ReadFile(
+                    client,
+                    line[read..].as_mut_ptr() as *mut _,
+                    (line.len() - read) as u32,
+                    &mut n,
+                    std::ptr::null_mut(),
+                )
+            };
+
+            assert!(ok.as_bool());
+            read += n as usize;
+        }
+
+        done.store(true, Ordering::Relaxed);
+        sending.join().unwrap();
+        unsafe { CloseHandle(client) };
+
+        assert_eq!(String::from_utf8(line).unwrap(), expected);
+    }
+}
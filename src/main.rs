@@ -1,60 +1,717 @@
-use std::{cell::Cell, pin::Pin, rc::Rc};
+#[cfg(feature = "backend-rts")]
+use std::{
+    cell::{Cell, RefCell},
+    pin::Pin,
+    rc::Rc,
+};
+
+#[cfg(feature = "backend-rts")]
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
 
 use thiserror::Error;
 
+#[cfg(all(target_os = "windows", feature = "backend-hid"))]
+pub mod hid;
+#[cfg(all(target_os = "windows", feature = "backend-rts", feature = "ipc"))]
+mod ipc;
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+pub mod pointer;
+#[cfg(all(target_os = "windows", feature = "replay"))]
+pub mod replay;
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+mod rts;
+#[cfg(all(target_os = "windows", feature = "strokes"))]
+pub mod stroke;
 #[cfg(target_os = "windows")]
 pub mod win32;
+#[cfg(all(target_os = "windows", feature = "backend-hid"))]
+pub use hid::HidTablet;
+#[cfg(all(target_os = "windows", feature = "backend-wintab"))]
+pub mod wintab;
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+pub use rts::EnableGuard;
 use win32::WinTabEvent;
 #[cfg(target_os = "windows")]
-pub use win32::WinTabletIndex;
+pub use win32::{
+    Contact, ContactKind, CursorState, EventMask, InputSource, NormalizedSample, PacketStatus,
+    StylusSample, SystemGesture, TipType, WinTabletIndex,
+};
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::BOOL;
 use windows::Win32::Foundation::HANDLE_PTR;
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+use windows::Win32::Foundation::HWND;
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+use windows::Win32::UI::TabletPC::{IInkDrawingAttributes, IRealTimeStylus, IStylusAsyncPlugin};
 #[cfg(target_os = "windows")]
-use windows::Win32::UI::TabletPC::IRealTimeStylus;
+use windows::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETMENUDROPALIGNMENT};
+#[cfg(all(target_os = "windows", feature = "backend-wintab"))]
+pub use wintab::WinTabTablet;
 
 #[cfg(target_os = "windows")]
 type Message = windows::core::HSTRING;
 
+#[cfg(all(
+    target_os = "windows",
+    not(any(
+        feature = "backend-rts",
+        feature = "backend-wintab",
+        feature = "backend-hid"
+    ))
+))]
+compile_error!(
+    "no tablet backend is enabled, enable `backend-rts`, `backend-wintab` or `backend-hid`"
+);
+
 ///
 #[derive(Error, Clone, Debug)]
 pub enum EasyTabError {
     #[cfg(target_os = "windows")]
     #[error("win error: {0}")]
     WinError(Message),
+    #[error("invalid guid: {0}")]
+    InvalidGuid(String),
+    #[error("no window is in the foreground")]
+    NoForegroundWindow,
+    #[error("the tablet isn't connected to a window")]
+    NotInitialized,
+    #[error("the window handle isn't a win32 handle")]
+    UnsupportedHandle,
+    #[error("no tablet is connected")]
+    NoTablet,
+    #[error("timed out waiting for the stylus")]
+    Timeout,
+    #[error("can't wait for events from inside a listener")]
+    InListener,
+    #[cfg(feature = "backend-wintab")]
+    #[error("wintab error: {0}")]
+    WinTab(&'static str),
+    #[cfg(feature = "serde")]
+    #[error("config error: {0}")]
+    Config(String),
 }
 
 pub type EasyTabResult<T> = std::result::Result<T, EasyTabError>;
 
+/// The response from a listener, used to decide whether the default processing of an event should still happen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventResponse {
+    /// Let the event continue through the default processing.
+    #[default]
+    Continue,
+    /// Mark the event as consumed, stopping the default processing where the event allows it.
+    ///
+    /// For packets, this keeps them from the other async plugins on the stylus. Synchronous plugins have already seen
+    /// them by the time the listeners are called. For [system gestures](WinTabEvent::SystemGesture), this turns off the
+    /// action Windows ties to the gesture where it can.
+    Handled,
+    /// Mark the event as [handled](Self::Handled), and don't call any more listeners for it. Useful for a high priority
+    /// listener (refer to [`EasyTablet::add_filtered_listener`]) that captures the input.
+    Stop,
+}
+
+/// An identifier for a registered listener, used to remove it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListenerId(usize);
+
+/// A rectangle, in whichever coordinate space it is used with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    /// The width of the rectangle.
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    /// The height of the rectangle.
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// Whether a point is inside the rectangle. The right and bottom edges are exclusive.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+}
+
+impl From<Rect> for TabletBounds {
+    fn from(rect: Rect) -> Self {
+        Self {
+            min_x: rect.left,
+            min_y: rect.top,
+            max_x: rect.right,
+            max_y: rect.bottom,
+        }
+    }
+}
+
+/// A non-fatal problem, reported through the event callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Warning {
+    /// [`EasyTabOptions::target_monitor`] doesn't refer to a connected monitor, so the tablet is mapped to the whole
+    /// desktop instead.
+    InvalidMonitor(usize),
+    /// [`EasyTabOptions::suppress_touch_feedback`] is set, but the feedback couldn't be turned off (e.g. on a version of
+    /// Windows before 8).
+    TouchFeedbackNotSuppressed,
+}
+
+/// The range of coordinates a tablet reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TabletBounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl TabletBounds {
+    /// The width of the bounds.
+    pub fn width(&self) -> i32 {
+        self.max_x - self.min_x
+    }
+
+    /// The height of the bounds.
+    pub fn height(&self) -> i32 {
+        self.max_y - self.min_y
+    }
+
+    /// Maps a point within the bounds to `[0, 1]` on both axes.
+    ///
+    /// An axis with no extent maps to `0.0`.
+    #[must_use]
+    pub fn normalize(&self, x: i32, y: i32) -> (f32, f32) {
+        let norm = |v: i32, min: i32, len: i32| {
+            if len == 0 {
+                0.0
+            } else {
+                (v - min) as f32 / len as f32
+            }
+        };
+
+        (
+            norm(x, self.min_x, self.width()),
+            norm(y, self.min_y, self.height()),
+        )
+    }
+
+    // maps `[x0, y0, x1, y1]` fractions of the bounds to the rect they cover, the reverse of `normalize`. the
+    // fractions are clamped to `0.0..=1.0`, and may be given in either order.
+    #[cfg(feature = "backend-rts")]
+    pub(crate) fn denormalize_rect(&self, rect: [f32; 4]) -> Rect {
+        let [x0, y0, x1, y1] = rect.map(|f| f.clamp(0.0, 1.0));
+
+        let x = |f: f32| self.min_x + (self.width() as f32 * f).round() as i32;
+        let y = |f: f32| self.min_y + (self.height() as f32 * f).round() as i32;
+
+        Rect {
+            left: x(x0.min(x1)),
+            top: y(y0.min(y1)),
+            right: x(x0.max(x1)),
+            bottom: y(y0.max(y1)),
+        }
+    }
+}
+
+/// Describes a tablet that was connected. Refer to [`EasyTablet::on_connect`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TabletInfo {
+    /// The name of the tablet, as the driver reports it.
+    pub name: String,
+    /// The plug and play id of the tablet.
+    pub plug_and_play_id: String,
+}
+
+/// Whether the tablet is built into a display or separate from it. Refer to [`EasyTablet::tablet_kind`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TabletKind {
+    /// The pen is used directly on a display (e.g. a pen display or a tablet PC).
+    Integrated,
+    /// The tablet is separate from the display.
+    External,
+    /// The kind couldn't be worked out.
+    #[default]
+    Unknown,
+}
+
+/// What the connected tablet supports. Refer to [`EasyTablet::capabilities`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether the tablet reports pressure.
+    pub pressure: bool,
+    /// Whether the tablet reports the tilt of the stylus.
+    pub tilt: bool,
+    /// Whether the tablet reports the rotation of the stylus about its own axis.
+    pub twist: bool,
+    /// Whether the tablet reports the yaw, pitch or roll of the stylus.
+    pub rotation: bool,
+    /// Whether the tablet reports the height of the stylus above the digitiser.
+    pub z: bool,
+    /// Whether an eraser has been used with the tablet.
+    pub eraser: bool,
+    /// Whether the tablet can track more than one contact at once.
+    pub multi_touch: bool,
+    /// The number of buttons on the cursor that last interacted with the tablet.
+    pub buttons: u32,
+    /// Whether the cursor that last interacted with the tablet can [air tap](crate::win32::WinTabEvent::AirTap), i.e.
+    /// it has a button to click while hovering.
+    pub air_tap: bool,
+}
+
+/// A workaround for a device that doesn't report its data the way the tablet APIs describe. Refer to
+/// [`EasyTablet::applied_quirks`].
+#[cfg(feature = "quirks")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quirk {
+    /// The y tilt is reported with the opposite sign, so it is flipped back.
+    InvertedTiltY,
+    /// The pressure never gets past this raw value, even though the device reports a larger maximum, so the pressure
+    /// is normalised against it instead.
+    PressureMax(i32),
+}
+
+/// How [`EasyTabOptions::retry_on_change`] retries re-initialising a tablet.
+///
+/// After each failed attempt the delay is multiplied by `backoff_factor`, up to `max_delay`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryOptions {
+    /// How many times to retry after the first attempt fails.
+    pub max_retries: u32,
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+    /// The largest delay between retries.
+    pub max_delay: Duration,
+    /// The factor the delay grows by after each failed attempt.
+    pub backoff_factor: u32,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_delay: Duration::from_millis(25),
+            max_delay: Duration::from_millis(400),
+            backoff_factor: 2,
+        }
+    }
+}
+
+/// The coordinate space of the positions in the events. Refer to [`EasyTabOptions::emit_coordinate_space`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoordinateSpace {
+    /// Tablet (ink) coordinates, or the [logical space](EasyTabOptions::logical_size) when set.
+    #[default]
+    Tablet,
+    /// Screen coordinates, as returned by [`EasyTablet::to_screen`].
+    Screen,
+    /// Client coordinates of the bound window, as returned by [`EasyTablet::to_window`].
+    Window,
+}
+
+/// The hand the stylus is held in. Refer to [`EasyTabOptions::handedness`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+impl Handedness {
+    /// Reads the handedness set in the Windows settings ("Tablet PC Settings", or "Pen & Windows Ink" on Windows 10
+    /// and later). Defaults to [`Handedness::Right`] if it can't be read.
+    #[cfg(target_os = "windows")]
+    pub fn system() -> Self {
+        let mut right_aligned = BOOL::from(true);
+
+        // the setting is stored as which side menus drop down on. for right-handed users they open to the left of the
+        // pen, so the hand doesn't cover them.
+        unsafe {
+            SystemParametersInfoW(
+                SPI_GETMENUDROPALIGNMENT,
+                0,
+                &mut right_aligned as *mut _ as *mut _,
+                Default::default(),
+            )
+        };
+
+        if right_aligned.as_bool() {
+            Handedness::Right
+        } else {
+            Handedness::Left
+        }
+    }
+
+    /// Returns where to place the left edge of a menu or palette `width` wide, opened from the pen at `x`, so the hand
+    /// doesn't cover it. This is the side Windows opens its own menus on: to the left of the pen for right-handed users,
+    /// and to the right for left-handed ones.
+    pub fn menu_x(self, x: i32, width: i32) -> i32 {
+        match self {
+            Handedness::Right => x - width,
+            Handedness::Left => x,
+        }
+    }
+}
+
+/// How two taps are recognised as a double tap. Refer to [`EasyTabOptions::double_tap_config`].
+///
+/// A tap is the stylus touching and lifting again within `interval`, without moving more than `max_distance`. Two taps
+/// make a double tap when the second ends within `interval` of the first, no further than `max_distance` away.
+#[cfg(feature = "gestures")]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoubleTapConfig {
+    /// The longest a tap can last, and the longest time between the two taps.
+    pub interval: Duration,
+    /// How far the stylus can move during a tap, and how far apart the two taps can be, in the same units as the
+    /// samples.
+    pub max_distance: i32,
+}
+
+#[cfg(feature = "gestures")]
+impl Default for DoubleTapConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(400),
+            max_distance: 300,
+        }
+    }
+}
+
+/// Converts a tilt along the x and y axes into an azimuth and altitude, all in degrees.
+///
+/// The azimuth is the direction the stylus leans in, clockwise from the positive x axis in `[0, 360)`. The altitude is
+/// the angle between the stylus and the surface, where `90` is perpendicular.
+pub fn tilt_to_spherical(x_tilt: f32, y_tilt: f32) -> (f32, f32) {
+    let tan_x = x_tilt.to_radians().tan();
+    let tan_y = y_tilt.to_radians().tan();
+
+    let azimuth = tan_y.atan2(tan_x).to_degrees().rem_euclid(360.0);
+    let altitude = (1.0 / tan_x.hypot(tan_y)).atan().to_degrees();
+
+    (azimuth, altitude)
+}
+
 /// The initialisation options for the tablet.
-#[derive(Default)]
+///
+/// With the `serde` feature, the options can be serialised, and saved and loaded with [`EasyTablet::save_config`] and
+/// [`EasyTablet::load_config`]. Fields missing from a saved config keep their defaults.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct EasyTabOptions {
     /// When a tablet is disconnected and either reconnected, or a new tablet is connected, it will try to re-initialise the new tablet.
+    ///
+    /// Can be changed later with [`EasyTablet::set_retry_on_change`].
     pub retry_on_change: bool,
+    /// How re-initialisation is retried when [`retry_on_change`](Self::retry_on_change) is set.
+    ///
+    /// The retries wait on a timer, so they need the message loop of the thread the tablet was created on to be running,
+    /// as the stylus does.
+    pub retry: RetryOptions,
     #[cfg(target_os = "windows")]
     pub index: WinTabletIndex,
+    /// The index of the monitor the tablet should map to. When `None`, the tablet maps to the whole desktop.
+    ///
+    /// Monitors are indexed in the order the OS enumerates them. Where the driver supports it, input is also restricted
+    /// to the monitor's region.
+    pub target_monitor: Option<usize>,
+    /// Map the tablet onto the client area of the bound window, rather than a monitor or the desktop. Useful when the
+    /// tablet is bound to a canvas control (a child window), so the whole tablet covers the canvas.
+    ///
+    /// The client area is read each time a point is mapped, so the mapping follows the window as it moves or resizes.
+    pub map_to_window: bool,
+    /// Map the tablet onto the display Windows maps the pen to (as set in the pen settings), rather than the whole
+    /// desktop, so the mapping matches the OS cursor. Ignored when [`target_monitor`](Self::target_monitor) is set.
+    ///
+    /// **Note**: Requires Windows 8 or later. On older versions, or when Windows doesn't report a mapping, the tablet
+    /// maps to the whole desktop.
+    pub use_os_mapping: bool,
+    /// The region of the tablet, in tablet coordinates, that is mapped onto the screen. When `None`, the whole tablet is
+    /// mapped.
+    ///
+    /// Points outside the region are clamped to its edges.
+    pub active_area: Option<Rect>,
+    /// The raw pressure range, as `(min, max)`, to normalise pressure against instead of the one the device reports.
+    /// When `None`, the device's range is used.
+    ///
+    /// Can be changed later with [`EasyTablet::set_pressure_range`].
+    pub pressure_range: Option<(i32, i32)>,
+    /// Pressure (normalised to `0.0..=1.0`) below this is treated as zero, and a contact doesn't count as touching the
+    /// digitiser until its pressure gets past it. Useful for pens that register light pressure when resting.
+    #[cfg(feature = "filters")]
+    pub pressure_deadzone: f32,
+    /// Drop [`WinTabEvent::StylusMove`] events with zero pressure, so only moves that actually draw are delivered.
+    /// Hover events are unaffected.
+    #[cfg(feature = "filters")]
+    pub require_pressure_for_move: bool,
+    /// For devices that don't report pressure (e.g. a mouse or a basic digitiser), report a pressure of `1.0` while the
+    /// contact is down and `0.0` otherwise, so code that expects pressure still works.
+    pub synth_pressure: bool,
+    /// Emit [`WinTabEvent::PressureChanged`] whenever the pressure changes by more than this since the last time it was
+    /// emitted. When `None`, the event isn't emitted.
+    #[cfg(feature = "filters")]
+    pub pressure_change_threshold: Option<f32>,
+    /// Emit [`WinTabEvent::PressureAbove`] when the pressure rises past this, and [`WinTabEvent::PressureBelow`] when
+    /// it falls back under it. When `None`, the events aren't emitted.
+    ///
+    /// Unlike [`pressure_deadzone`](Self::pressure_deadzone), this doesn't change the samples, it only notifies.
+    #[cfg(feature = "filters")]
+    pub pressure_threshold: Option<f32>,
+    /// How far the pressure has to fall under [`pressure_threshold`](Self::pressure_threshold) before
+    /// [`WinTabEvent::PressureBelow`] is emitted, so pressure hovering around the threshold doesn't flip back and forth.
+    #[cfg(feature = "filters")]
+    pub pressure_hysteresis: f32,
+    /// Don't apply the workarounds for devices known to misreport their data. Refer to
+    /// [`EasyTablet::applied_quirks`].
+    #[cfg(feature = "quirks")]
+    pub disable_quirks: bool,
+    /// Run [`EasyTablet::add_threaded_listener`] listeners on a dedicated dispatcher thread, rather than the thread the
+    /// tablet was created on. Events reach the dispatcher thread in the order they were raised.
+    ///
+    /// Useful for keeping event processing off the UI thread, at the cost of the listeners having to be `Send` and
+    /// not being able to mark events as handled.
+    #[cfg(feature = "dispatch-thread")]
+    pub dispatch_thread: bool,
+    /// Only copy the packets in the stylus callbacks, and decode them when [`EasyTablet::next_event`] or
+    /// [`EasyTablet::drain_events`] is called, to spend as little time as possible on the input thread.
+    ///
+    /// The listeners are called when the packets are decoded, rather than when they arrive, so they can't mark packets
+    /// as handled.
+    pub lazy_decode: bool,
+    /// Drop [`WinTabEvent::StylusMove`] and [`WinTabEvent::HoverMove`] events that arrive sooner than `1 / max_event_hz`
+    /// seconds after the last one delivered, for apps that can't keep up with the tablet's report rate. Every other
+    /// event is still delivered.
+    ///
+    /// Unlike coalescing, the dropped samples are lost, and the getters only reflect the samples that were delivered.
+    #[cfg(feature = "filters")]
+    pub max_event_hz: Option<u32>,
+    /// Also emit [`WinTabEvent::RelativeMove`] with how far the stylus moved since the previous sample, for using the
+    /// tablet like a trackpad. The absolute events are still emitted.
+    ///
+    /// The first sample after the stylus comes into range (or after another cursor was used) doesn't produce a delta.
+    pub relative_mode: bool,
+    /// Report positions in a logical space of this width and height, rather than tablet coordinates, e.g. the size of a
+    /// fixed-size canvas. The tablet (or [`active_area`](Self::active_area) when set) is stretched over the whole space.
+    ///
+    /// [`EasyTablet::to_screen`] and [`EasyTablet::normalized_position`] take the logical space into account. Ignored
+    /// while a [coordinate mapper](EasyTablet::set_coordinate_mapper) is set.
+    pub logical_size: Option<(u32, u32)>,
+    /// Emit [`WinTabEvent::DoubleTap`] when two taps land in quick succession. When `None`, double taps aren't
+    /// recognised.
+    #[cfg(feature = "gestures")]
+    pub double_tap_config: Option<DoubleTapConfig>,
+    /// Lift a contact that hasn't sent any packets for this long, emitting [`WinTabEvent::StylusInactive`] to finish
+    /// its stroke. When `None`, contacts stay down until the tablet lifts them.
+    ///
+    /// Contacts are normally lifted when the stylus leaves the digitiser, or at the latest when it goes out of range.
+    /// This is a fallback for tablets that drop both when the pen is pulled away quickly. The timeout is checked on a
+    /// timer while contacts are down, which needs the thread's message loop to be running, so it should be comfortably
+    /// longer than the gaps between packets.
+    pub stroke_timeout: Option<std::time::Duration>,
+    /// Treat a contact that lifts and goes back down within this long as never having lifted, suppressing the
+    /// [`WinTabEvent::StylusInactive`] and [`WinTabEvent::StylusActive`] pair. Smooths out pens that chatter at the
+    /// contact threshold. When `None`, every lift is reported.
+    ///
+    /// **Note**: Lifts are reported this much later, once it's clear the contact isn't coming back.
+    pub contact_debounce: Option<std::time::Duration>,
+    /// Enable the tablet as part of [`EasyTablet::init_options`] (and the other initialisers that take options), so
+    /// [`EasyTablet::enable`] doesn't have to be called separately.
+    pub auto_enable: bool,
+    /// The coordinate space of the positions in the samples (and so the events and getters such as
+    /// [`EasyTablet::x`]), so listeners don't have to convert every position themselves.
+    ///
+    /// The conversion happens after a [coordinate mapper](EasyTablet::set_coordinate_mapper), if one is set, so it is
+    /// best left as [`CoordinateSpace::Tablet`] with one.
+    pub emit_coordinate_space: CoordinateSpace,
+    /// The hand the stylus is held in. This doesn't change the input, but is available from [`EasyTablet::handedness`]
+    /// so menus and palettes can be placed where the hand won't cover them. [`Handedness::menu_x`] places them on the
+    /// side Windows uses by default.
+    ///
+    /// Use [`Handedness::system`] to follow the handedness set in the Windows settings.
+    pub handedness: Handedness,
+    /// Turn off the visual feedback Windows draws on the bound window for touch contacts and pen taps (e.g. the ripple
+    /// on a tap), which gets in the way while drawing. Applied when the tablet is [connected](EasyTablet::connect).
+    ///
+    /// **Note**: Requires Windows 8 or later. On older versions the feedback stays, and
+    /// [`Warning::TouchFeedbackNotSuppressed`] is emitted instead.
+    pub suppress_touch_feedback: bool,
+    /// Report the points from [`EasyTablet::to_screen`] and [`EasyTablet::to_window`] in device-independent pixels
+    /// (1/96th of an inch) rather than physical pixels, so they line up with DPI-scaled UI.
+    ///
+    /// They are scaled by the [DPI](EasyTablet::dpi) of the bound window.
+    pub report_dips: bool,
 }
 
 // transparent, private wrapper struct since `EasyTablet` needs to wrapped in an `Rc`, but I don't want to expose the `Rc` to the user.
 // especially since it would require them to write `Rc<EasyTablet>` everywhere, rather than `EasyTablet`.
 /// Private inner struct, do not use. (Use [`EasyTablet`] instead)
 #[doc(hidden)]
+#[cfg(feature = "backend-rts")]
 pub struct __InnerTablet {
     active: Cell<bool>,
     x: Cell<i32>,
     y: Cell<i32>,
     pressure: Cell<f32>,
+    z: Cell<Option<i32>>,
+    bounds: Cell<Option<TabletBounds>>,
+    #[cfg(target_os = "windows")]
+    last_sample: Cell<Option<StylusSample>>,
+    // the screen-space rect the tablet is mapped onto
+    mapping_target: Cell<Rect>,
+    // the client area of the bound window in screen coordinates, refreshed as the stylus comes into range
+    client_rect: Cell<Option<Rect>>,
+    // whether the primary contact is over the client area
+    in_window: Cell<bool>,
+    // the dpi of the bound window, as of the last mapping update
+    dpi: Cell<u32>,
+    // the region of the tablet that is mapped
+    active_area: Cell<Option<Rect>>,
+    // the id of the cursor (pen tip, eraser, finger...) that last interacted with the tablet
+    cursor_id: Cell<u32>,
+    // the tablet context that last sent packets
+    tablet_context: Cell<Option<u32>>,
+    // cleared whenever a tablet is connected or disconnected
+    capabilities: Cell<Option<Capabilities>>,
+    // how many samples have landed in each pressure bin, for `EasyTablet::pressure_histogram`
+    #[cfg(all(target_os = "windows", feature = "histogram"))]
+    pressure_histogram: RefCell<[u32; rts::HISTOGRAM_BINS]>,
+    // the pressure last reported through `WinTabEvent::PressureChanged`
+    #[cfg(feature = "filters")]
+    reported_pressure: Cell<f32>,
+    // when the last move or hover event was delivered, for `EasyTabOptions::max_event_hz`
+    #[cfg(feature = "filters")]
+    last_move_at: Cell<Option<std::time::Instant>>,
+    // the cursor and position of the previous sample, for `EasyTabOptions::relative_mode`. cleared between strokes.
+    relative_origin: Cell<Option<(u32, i32, i32)>>,
+    // the cursor, time and position of the last contact to touch down, to recognise taps
+    #[cfg(feature = "gestures")]
+    tap_start: Cell<Option<(u32, std::time::Instant, i32, i32)>>,
+    // when and where the last tap ended, if it could still be the first half of a double tap
+    #[cfg(feature = "gestures")]
+    last_tap: Cell<Option<(std::time::Instant, i32, i32)>>,
+    // when a button was pressed while the stylus was hovering, until it's released
+    #[cfg(feature = "gestures")]
+    air_press: Cell<Option<std::time::Instant>>,
+    // whether the pressure is past `EasyTabOptions::pressure_threshold`
+    #[cfg(feature = "filters")]
+    above_threshold: Cell<bool>,
+    // the statistics of the stroke in progress, and of the last one to finish
+    #[cfg(all(target_os = "windows", feature = "strokes"))]
+    current_stroke: RefCell<Option<stroke::StatsBuilder>>,
+    #[cfg(all(target_os = "windows", feature = "strokes"))]
+    last_stroke: Cell<Option<stroke::StrokeStats>>,
+    // whether `WinTabEvent::MaxPressure` has been emitted during the current stroke
+    max_pressure_reached: Cell<bool>,
+    // the contacts currently touching the digitiser, keyed by cursor id
+    #[cfg(target_os = "windows")]
+    contacts: RefCell<HashMap<u32, Contact>>,
+    // when each contact last sent a packet, for `opts.stroke_timeout`
+    contact_seen: RefCell<HashMap<u32, std::time::Instant>>,
+    // the id of the timer checking `contact_seen` while contacts are down, or `0`
+    expiry_timer: Cell<usize>,
+    // the id of the timer for the next retry of reading a newly connected tablet, or `0`
+    retry_timer: Cell<usize>,
+    // a lift held back by `opts.contact_debounce`, with when it happened
+    pending_up: Cell<Option<(u32, std::time::Instant)>>,
+    // the first contact to go down while nothing else was. `None` once it lifts, until every contact has.
+    primary_contact: Cell<Option<u32>>,
+    // every cursor that has interacted with the tablet, keyed by cursor id
+    #[cfg(target_os = "windows")]
+    cursors: RefCell<HashMap<u32, win32::CursorState>>,
+
+    // starts as `opts.retry_on_change`, but can be toggled at runtime
+    retry_on_change: Cell<bool>,
 
     opts: EasyTabOptions,
+    // the thread the tablet was created on, which every event has to be dispatched on
+    thread: std::thread::ThreadId,
 
     #[cfg(target_os = "windows")]
-    on: Cell<Option<Box<dyn Fn(WinTabEvent)>>>,
+    listeners: RefCell<Vec<rts::Listener>>,
+    next_listener: Cell<usize>,
+    // the listener registered through `EasyTablet::on`, replaced by the next call
+    on_listener: Cell<Option<ListenerId>>,
+    // the most recent events, oldest first
+    #[cfg(target_os = "windows")]
+    queue: RefCell<VecDeque<WinTabEvent>>,
+    // forwards events to the dispatcher thread, with `EasyTabOptions::dispatch_thread`
+    #[cfg(all(target_os = "windows", feature = "dispatch-thread"))]
+    dispatcher: Option<std::sync::mpsc::Sender<rts::DispatcherMessage>>,
+    // the listeners registered on the dispatcher thread that haven't been removed
+    #[cfg(all(target_os = "windows", feature = "dispatch-thread"))]
+    threaded_listeners: RefCell<std::collections::HashSet<ListenerId>>,
+    // set while listeners are being called. events raised in the meantime are deferred until they return.
+    dispatching: Cell<bool>,
+    // set once the marker queued by `flush` comes back out of the stylus
+    flushed: Cell<bool>,
+    #[cfg(target_os = "windows")]
+    deferred: RefCell<VecDeque<WinTabEvent>>,
+    // packets copied out of the stylus callbacks but not decoded yet, with `EasyTabOptions::lazy_decode`
+    #[cfg(target_os = "windows")]
+    pending_packets: RefCell<VecDeque<rts::PendingPackets>>,
+    // the most recent samples and when they arrived, oldest first
+    #[cfg(all(target_os = "windows", feature = "history"))]
+    history: RefCell<VecDeque<(std::time::Instant, StylusSample)>>,
+    // the pipe the events are served over, and the listener feeding it
+    #[cfg(all(target_os = "windows", feature = "ipc"))]
+    ipc: RefCell<Option<(ListenerId, ipc::EventPipe)>>,
 
     #[cfg(target_os = "windows")]
     stylus: IRealTimeStylus,
+    #[cfg(target_os = "windows")]
+    hwnd: Cell<HWND>,
+    // whether the stylus has been bound to a window (see `EasyTablet::disconnected`)
+    connected: Cell<bool>,
+    // the plugin receiving the stylus' events, once connected. removed from the stylus when the tablet is dropped.
+    #[cfg(target_os = "windows")]
+    plugin: RefCell<Option<IStylusAsyncPlugin>>,
+    // created the first time they're needed, see `EasyTablet::drawing_attributes`
+    #[cfg(target_os = "windows")]
+    drawing_attributes: RefCell<Option<IInkDrawingAttributes>>,
+    // the gesture actions turned off on the bound window after a listener handled a system gesture, restored when the
+    // tablet is dropped
+    suppressed_gestures: Cell<u32>,
+    #[cfg(all(target_os = "windows", feature = "raw-packets"))]
+    raw_packet_callback: RefCell<Option<rts::RawPacketCallback>>,
+    // called when a tablet is connected to or disconnected from the system, see `EasyTablet::on_connect`
+    on_connect: RefCell<Option<rts::ConnectCallback>>,
+    on_disconnect: RefCell<Option<Box<dyn Fn()>>>,
+    // the raw pressure range to normalise against instead of the one the device reports, see
+    // `EasyTablet::set_pressure_range`
+    pressure_range: Cell<Option<(i32, i32)>>,
+    // replaces the built-in mapping when set, see `EasyTablet::set_coordinate_mapper`
+    coordinate_mapper: RefCell<Option<rts::CoordinateMapper>>,
+    // applied to every event before it's handled, see `EasyTablet::set_event_transform`
+    event_transform: RefCell<Option<rts::EventTransform>>,
+    // packet layouts, keyed by tablet context id
+    #[cfg(target_os = "windows")]
+    packet_descriptions: RefCell<HashMap<u32, Rc<rts::PacketDescription>>>,
+    // the stable ids of the tablets, keyed by tablet context id. cleared whenever a tablet is added or removed.
+    device_ids: RefCell<HashMap<u32, String>>,
 }
 
-/// TODO
+/// A tablet bound to a window.
+///
+/// Dropping the tablet disables it and detaches it from the stylus, along with its listeners.
+///
+/// **Note**: The tablet is tied to the thread it was created on. The underlying COM objects live in that thread's
+/// apartment and events are raised by its message loop, so `EasyTablet` is neither `Send` nor `Sync`.
+#[cfg(feature = "backend-rts")]
 pub struct EasyTablet(Rc<__InnerTablet>);
 
+#[cfg(feature = "backend-rts")]
 impl std::ops::Deref for EasyTablet {
     type Target = __InnerTablet;
 
@@ -63,14 +720,79 @@ impl std::ops::Deref for EasyTablet {
     }
 }
 
+/// A tablet API that events are read from.
+///
+/// [`Tablet`] is the backend selected with the `backend-*` features.
+#[cfg(target_os = "windows")]
+pub trait TabletBackend: Sized {
+    /// Binds a tablet to the given window. Refer to [`EasyTablet::init_options`].
+    fn open(hwnd: usize, opts: EasyTabOptions) -> EasyTabResult<Self>;
+
+    /// Binds a tablet with the default options to the given window.
+    fn init<W: Into<usize>>(hwnd: W) -> EasyTabResult<Self> {
+        Self::open(hwnd.into(), EasyTabOptions::default())
+    }
+
+    /// Binds a tablet with the default options to the window behind a [`RawWindowHandle`] (e.g. from winit).
+    ///
+    /// Fails with [`EasyTabError::UnsupportedHandle`] if the handle isn't a Win32 one.
+    fn init_from_handle(handle: RawWindowHandle) -> EasyTabResult<Self> {
+        Self::open(
+            win32::hwnd_from_handle(handle)?.0,
+            EasyTabOptions::default(),
+        )
+    }
+
+    /// Enables the tablet.
+    fn enable(&self) -> EasyTabResult<()>;
+
+    /// Disables the tablet.
+    fn disable(&self) -> EasyTabResult<()>;
+
+    /// Registers a callback that is called for every tablet event, returning an id to remove it with.
+    fn add_listener(&self, cb: Box<dyn Fn(WinTabEvent)>) -> ListenerId;
+
+    /// Removes a listener, returning `false` if no listener with the given id exists.
+    fn remove_listener(&self, id: ListenerId) -> bool;
+
+    /// Reads the input that arrived since the last call, calling the listeners for it, and returns the number of
+    /// packets read.
+    ///
+    /// Backends whose events are delivered through the window's message loop (such as RealTimeStylus) raise them as
+    /// they arrive, so this does nothing for them. Calling it whenever the message loop wakes up works for every
+    /// backend.
+    fn poll(&self) -> usize {
+        0
+    }
+}
+
+/// The tablet backend selected with the `backend-*` features.
+///
+/// `backend-hid` doesn't provide one, as [`HidTablet`] reads a device rather than being bound to a window.
+#[cfg(all(target_os = "windows", feature = "backend-rts"))]
+pub type Tablet = EasyTablet;
+/// The tablet backend selected with the `backend-*` features.
+///
+/// `backend-hid` doesn't provide one, as [`HidTablet`] reads a device rather than being bound to a window.
+#[cfg(all(
+    target_os = "windows",
+    feature = "backend-wintab",
+    not(feature = "backend-rts")
+))]
+pub type Tablet = WinTabTablet;
+
+#[cfg(any(feature = "backend-rts", feature = "backend-wintab"))]
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+#[cfg(any(feature = "backend-rts", feature = "backend-wintab"))]
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
 
+#[cfg(any(feature = "backend-rts", feature = "backend-wintab"))]
 fn main() {
     // unsafe {
     //     CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED).expect("failed to initalise COM")
@@ -79,19 +801,12 @@ fn main() {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    let handle = window.raw_window_handle();
-
-    let hwnd = match handle {
-        RawWindowHandle::Win32(r) => r,
-        _ => panic!(""),
-    };
-
-    let tablet = EasyTablet::init(hwnd.hwnd as usize).expect("tablet failed to initialize");
+    let tablet =
+        Tablet::init_from_handle(window.raw_window_handle()).expect("tablet failed to initialize");
+    tablet.add_listener(Box::new(|event| println!("new event {:#?}", event)));
 
     tablet.enable().expect("enable");
 
-    tablet.on(Box::new(|event| println!("new event {:#?}", event)));
-
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
 
@@ -100,7 +815,117 @@ fn main() {
                 event: WindowEvent::CloseRequested,
                 window_id,
             } if window_id == window.id() => *control_flow = ControlFlow::Exit,
+            // backends that aren't driven by the message loop are read whenever it wakes up
+            Event::MainEventsCleared => {
+                tablet.poll();
+            }
             _ => (),
         }
     });
 }
+
+#[cfg(not(any(feature = "backend-rts", feature = "backend-wintab")))]
+fn main() {
+    // the hid backend reads a device rather than a window, so it's given the device path
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: easytab-rs <hid device path>");
+    let tablet = HidTablet::open(&path).expect("tablet failed to open");
+
+    loop {
+        println!("new event {:#?}", tablet.next_event().expect("read"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near((a, b): (f32, f32), (x, y): (f32, f32)) {
+        assert!(
+            (a - x).abs() < 1e-3 && (b - y).abs() < 1e-3,
+            "({a}, {b}) != ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn upright_stylus_is_perpendicular() {
+        assert_near(tilt_to_spherical(0.0, 0.0), (0.0, 90.0));
+    }
+
+    #[test]
+    fn tilt_sets_the_direction_and_angle() {
+        assert_near(tilt_to_spherical(45.0, 0.0), (0.0, 45.0));
+        assert_near(tilt_to_spherical(0.0, 45.0), (90.0, 45.0));
+        assert_near(tilt_to_spherical(-45.0, 0.0), (180.0, 45.0));
+        assert_near(tilt_to_spherical(0.0, -30.0), (270.0, 60.0));
+    }
+
+    #[test]
+    fn bounds_normalize_to_unit_square() {
+        let bounds = TabletBounds {
+            min_x: 100,
+            min_y: -50,
+            max_x: 300,
+            max_y: 50,
+        };
+
+        assert_near(bounds.normalize(100, -50), (0.0, 0.0));
+        assert_near(bounds.normalize(200, 0), (0.5, 0.5));
+        assert_near(bounds.normalize(300, 50), (1.0, 1.0));
+    }
+
+    #[test]
+    fn empty_bounds_normalize_to_zero() {
+        let bounds = TabletBounds {
+            min_x: 10,
+            min_y: 0,
+            max_x: 10,
+            max_y: 100,
+        };
+
+        assert_near(bounds.normalize(10, 25), (0.0, 0.25));
+    }
+
+    #[cfg(feature = "backend-rts")]
+    #[test]
+    fn fractions_denormalize_to_a_rect() {
+        let bounds = TabletBounds {
+            min_x: 100,
+            min_y: -50,
+            max_x: 300,
+            max_y: 50,
+        };
+
+        let half = bounds.denormalize_rect([0.0, 0.0, 0.5, 0.5]);
+
+        assert_eq!(
+            half,
+            Rect {
+                left: 100,
+                top: -50,
+                right: 200,
+                bottom: 0,
+            }
+        );
+        assert_near(bounds.normalize(half.right, half.bottom), (0.5, 0.5));
+
+        // swapped corners, and fractions outside the surface
+        assert_eq!(
+            bounds.denormalize_rect([1.5, 0.75, 0.25, -1.0]),
+            Rect {
+                left: 150,
+                top: -50,
+                right: 300,
+                bottom: 25,
+            }
+        );
+    }
+
+    #[test]
+    fn menus_open_away_from_the_hand() {
+        assert_eq!(Handedness::Right.menu_x(500, 200), 300);
+        assert_eq!(Handedness::Left.menu_x(500, 200), 500);
+        assert_eq!(Handedness::default().menu_x(500, 200), 300);
+    }
+}
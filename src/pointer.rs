@@ -0,0 +1,275 @@
+// converts tablet events into events shaped like the W3C pointer events (https://www.w3.org/TR/pointerevents/), for
+// code ported from the web
+
+use crate::win32::{ContactKind, PacketStatus, StylusSample, WinTabEvent};
+use crate::EasyTablet;
+
+/// The `buttons` bit for a pen touching the digitiser (or the left mouse button).
+pub const BUTTON_CONTACT: u16 = 1;
+/// The `buttons` bit for the barrel button of a pen (or the right mouse button).
+pub const BUTTON_BARREL: u16 = 2;
+/// The `buttons` bit for the eraser end of a pen touching the digitiser.
+pub const BUTTON_ERASER: u16 = 32;
+
+/// What a [`PointerEvent`] is, mirroring the `pointerdown`, `pointerup` and `pointermove` events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerEventKind {
+    /// The first button was pressed, or the pen touched the digitiser.
+    Down,
+    /// The last button was released, or the pen left the digitiser.
+    Up,
+    /// The pointer moved, or a button changed while another was held.
+    Move,
+}
+
+/// The kind of device behind a [`PointerEvent`], mirroring `pointerType`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerType {
+    Mouse,
+    Pen,
+    Touch,
+}
+
+/// A tablet event in the shape of a W3C `PointerEvent`. Refer to [`from_event`](Self::from_event).
+///
+/// The fields follow the spec, except that the position is in the same coordinates as the samples (tablet coordinates
+/// unless [`EasyTabOptions::emit_coordinate_space`](crate::EasyTabOptions::emit_coordinate_space) says otherwise)
+/// rather than client coordinates. Use [`EasyTablet::to_window`] to get the latter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerEvent {
+    pub kind: PointerEventKind,
+    /// The id of the cursor the event is from. Refer to [`StylusSample::cursor`].
+    pub pointer_id: u32,
+    pub pointer_type: PointerType,
+    /// The x position of the pointer, in the same coordinates as the samples.
+    pub x: i32,
+    /// The y position of the pointer, in the same coordinates as the samples.
+    pub y: i32,
+    /// The pressure, normalised to `0.0..=1.0`. Always `0.0` while hovering.
+    pub pressure: f32,
+    /// The tilt along the x axis in degrees, in `-90..=90`. `0` if the tablet doesn't report tilt.
+    pub tilt_x: i32,
+    /// The tilt along the y axis in degrees, in `-90..=90`. `0` if the tablet doesn't report tilt.
+    pub tilt_y: i32,
+    /// The rotation of the pen around its lengthwise axis in degrees, in `0..=359`. `0` if the pen doesn't report it.
+    pub twist: i32,
+    /// The buttons held, as a combination of [`BUTTON_CONTACT`], [`BUTTON_BARREL`] and [`BUTTON_ERASER`].
+    pub buttons: u16,
+}
+
+impl PointerEvent {
+    /// Converts an event into a pointer event, or `None` for events without a pointer equivalent (e.g.
+    /// [`WinTabEvent::MappingChanged`]).
+    ///
+    /// The tablet fills in what the event doesn't carry (e.g. the position for [`WinTabEvent::StylusActive`]), so this
+    /// is meant to be called from a listener, with the event just received.
+    ///
+    /// ```
+    /// tablet.add_listener(Box::new(move |event| {
+    ///     if let Some(pointer) = PointerEvent::from_event(&tablet, &event) {
+    ///         canvas.handle_pointer(pointer);
+    ///     }
+    /// }));
+    /// ```
+    pub fn from_event(tablet: &EasyTablet, event: &WinTabEvent) -> Option<Self> {
+        let last = tablet.last_sample().unwrap_or_default();
+
+        let (kind, sample, barrel) = match *event {
+            WinTabEvent::StylusActive => (PointerEventKind::Down, last, None),
+            WinTabEvent::StylusInactive => (PointerEventKind::Up, last, None),
+            WinTabEvent::StylusMove(sample) | WinTabEvent::HoverMove(sample) => {
+                (PointerEventKind::Move, sample, None)
+            }
+
+            // a button only counts as down or up when no other button is held
+            WinTabEvent::StylusButtonDown(x, y) => (
+                if tablet.active() {
+                    PointerEventKind::Move
+                } else {
+                    PointerEventKind::Down
+                },
+                StylusSample { x, y, ..last },
+                Some(true),
+            ),
+            WinTabEvent::StylusButtonUp(x, y) => (
+                if tablet.active() {
+                    PointerEventKind::Move
+                } else {
+                    PointerEventKind::Up
+                },
+                StylusSample { x, y, ..last },
+                Some(false),
+            ),
+
+            _ => return None,
+        };
+
+        let contact = tablet
+            .state_for_cursor(sample.cursor)
+            .map(|state| state.kind)
+            .unwrap_or(ContactKind::Pen);
+
+        Some(Self::from_sample(
+            kind,
+            sample,
+            barrel,
+            contact,
+            tablet.active(),
+        ))
+    }
+
+    // builds the pointer event for a sample. `barrel` overrides the barrel button state in the sample, and `active` is
+    // whether the contact is touching the digitiser.
+    fn from_sample(
+        kind: PointerEventKind,
+        sample: StylusSample,
+        barrel: Option<bool>,
+        contact: ContactKind,
+        active: bool,
+    ) -> Self {
+        let status = sample.status.unwrap_or_default();
+        let mut buttons = 0;
+
+        // the contact is released by the time `StylusInactive` arrives
+        if active && kind != PointerEventKind::Up {
+            buttons |= if contact == ContactKind::Eraser || status.contains(PacketStatus::INVERTED)
+            {
+                BUTTON_ERASER
+            } else {
+                BUTTON_CONTACT
+            };
+        }
+
+        if barrel.unwrap_or_else(|| status.contains(PacketStatus::BARREL)) {
+            buttons |= BUTTON_BARREL;
+        }
+
+        let (tilt_x, tilt_y) = sample.tilt.unwrap_or_default();
+
+        Self {
+            kind,
+            pointer_id: sample.cursor,
+            pointer_type: match contact {
+                ContactKind::Pen | ContactKind::Eraser => PointerType::Pen,
+                ContactKind::Touch => PointerType::Touch,
+                ContactKind::Mouse => PointerType::Mouse,
+            },
+            x: sample.x,
+            y: sample.y,
+            pressure: if buttons & (BUTTON_CONTACT | BUTTON_ERASER) != 0 {
+                sample.pressure
+            } else {
+                0.0
+            },
+            tilt_x: (tilt_x.round() as i32).clamp(-90, 90),
+            tilt_y: (tilt_y.round() as i32).clamp(-90, 90),
+            twist: sample
+                .twist
+                .map_or(0, |twist| (twist.round() as i32).rem_euclid(360)),
+            buttons,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touching_pen_reports_pressure_and_contact() {
+        let sample = StylusSample {
+            x: 10,
+            y: 20,
+            pressure: 0.5,
+            tilt: Some((30.4, -95.0)),
+            twist: Some(-90.0),
+            cursor: 3,
+            ..Default::default()
+        };
+
+        let event =
+            PointerEvent::from_sample(PointerEventKind::Move, sample, None, ContactKind::Pen, true);
+
+        assert_eq!(
+            event,
+            PointerEvent {
+                kind: PointerEventKind::Move,
+                pointer_id: 3,
+                pointer_type: PointerType::Pen,
+                x: 10,
+                y: 20,
+                pressure: 0.5,
+                tilt_x: 30,
+                tilt_y: -90,
+                twist: 270,
+                buttons: BUTTON_CONTACT,
+            }
+        );
+    }
+
+    #[test]
+    fn hovering_pen_has_no_pressure() {
+        let sample = StylusSample {
+            pressure: 0.5,
+            ..Default::default()
+        };
+
+        let event = PointerEvent::from_sample(
+            PointerEventKind::Move,
+            sample,
+            None,
+            ContactKind::Pen,
+            false,
+        );
+
+        assert_eq!(event.buttons, 0);
+        assert_eq!(event.pressure, 0.0);
+    }
+
+    #[test]
+    fn inverted_pen_uses_the_eraser_button() {
+        let sample = StylusSample {
+            status: Some(PacketStatus::INVERTED | PacketStatus::BARREL),
+            ..Default::default()
+        };
+
+        let event =
+            PointerEvent::from_sample(PointerEventKind::Down, sample, None, ContactKind::Pen, true);
+
+        assert_eq!(event.buttons, BUTTON_ERASER | BUTTON_BARREL);
+    }
+
+    #[test]
+    fn barrel_override_replaces_the_status() {
+        let sample = StylusSample {
+            status: Some(PacketStatus::BARREL),
+            ..Default::default()
+        };
+
+        let event = PointerEvent::from_sample(
+            PointerEventKind::Up,
+            sample,
+            Some(false),
+            ContactKind::Touch,
+            true,
+        );
+
+        // released contacts don't hold a button, even while the tablet still reports them as active
+        assert_eq!(event.buttons, 0);
+        assert_eq!(event.pointer_type, PointerType::Touch);
+    }
+
+    #[test]
+    fn missing_tilt_and_twist_are_zero() {
+        let event = PointerEvent::from_sample(
+            PointerEventKind::Move,
+            StylusSample::default(),
+            None,
+            ContactKind::Mouse,
+            false,
+        );
+
+        assert_eq!((event.tilt_x, event.tilt_y, event.twist), (0, 0, 0));
+        assert_eq!(event.pointer_type, PointerType::Mouse);
+    }
+}
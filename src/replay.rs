@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::win32::WinTabEvent;
+use crate::{ListenerId, TabletBackend};
+
+/// The events recorded from a tablet, each with how long after the start of the recording it arrived.
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    pub events: Vec<(Duration, WinTabEvent)>,
+}
+
+impl Session {
+    /// Calls `f` with each event, waiting between them as long as the original recording did, scaled by
+    /// `replay_speed` (e.g. `2.0` replays twice as fast, `0.5` half as fast).
+    ///
+    /// A `replay_speed` that isn't positive replays as fast as possible. Refer to
+    /// [`replay_as_fast_as_possible`](Self::replay_as_fast_as_possible).
+    pub fn replay(&self, replay_speed: f32, f: impl FnMut(WinTabEvent)) {
+        let start = Instant::now();
+
+        self.replay_with(replay_speed, || start.elapsed(), std::thread::sleep, f);
+    }
+
+    // replays the events against the given clock, where `elapsed` is the time since the replay started and `sleep`
+    // waits for the given time
+    fn replay_with(
+        &self,
+        replay_speed: f32,
+        elapsed: impl Fn() -> Duration,
+        mut sleep: impl FnMut(Duration),
+        mut f: impl FnMut(WinTabEvent),
+    ) {
+        if replay_speed.is_nan() || replay_speed <= 0.0 {
+            self.replay_as_fast_as_possible(f);
+            return;
+        }
+
+        for (offset, event) in &self.events {
+            let due = offset.div_f32(replay_speed);
+            let elapsed = elapsed();
+
+            if due > elapsed {
+                sleep(due - elapsed);
+            }
+
+            f(*event);
+        }
+    }
+
+    /// Calls `f` with each event straight away, ignoring the original timing.
+    pub fn replay_as_fast_as_possible(&self, mut f: impl FnMut(WinTabEvent)) {
+        for (_, event) in &self.events {
+            f(*event);
+        }
+    }
+
+    /// How long the recording lasted, up to its last event.
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map(|(offset, _)| *offset)
+            .unwrap_or_default()
+    }
+}
+
+/// Records the events from a tablet into a [`Session`], to be replayed later (e.g. to reproduce a bug).
+///
+/// ```
+/// let recorder = SessionRecorder::new(&tablet);
+/// //...
+/// let session = recorder.finish(&tablet);
+/// session.replay(2.0, |event| handle(event));
+/// ```
+pub struct SessionRecorder {
+    id: ListenerId,
+    session: Rc<RefCell<Session>>,
+}
+
+impl SessionRecorder {
+    /// Starts recording the events from the tablet.
+    pub fn new(tablet: &impl TabletBackend) -> Self {
+        let session: Rc<RefCell<Session>> = Rc::default();
+        let start = Instant::now();
+
+        let id = tablet.add_listener({
+            let session = Rc::clone(&session);
+
+            Box::new(move |event| session.borrow_mut().events.push((start.elapsed(), event)))
+        });
+
+        Self { id, session }
+    }
+
+    /// Stops recording, returning the recorded session.
+    pub fn finish(self, tablet: &impl TabletBackend) -> Session {
+        tablet.remove_listener(self.id);
+
+        self.session.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            events: vec![
+                (Duration::ZERO, WinTabEvent::StylusActive),
+                (Duration::from_millis(100), WinTabEvent::MaxPressure),
+                (Duration::from_millis(400), WinTabEvent::StylusInactive),
+            ],
+        }
+    }
+
+    // replays the session against a fake clock that only moves when slept, returning when each event was delivered
+    fn replay_timed(session: &Session, replay_speed: f32) -> Vec<Duration> {
+        let now = Cell::new(Duration::ZERO);
+        let mut delivered = Vec::new();
+
+        session.replay_with(
+            replay_speed,
+            || now.get(),
+            |wait| now.set(now.get() + wait),
+            |_| delivered.push(now.get()),
+        );
+
+        delivered
+    }
+
+    #[test]
+    fn replay_keeps_the_original_timing() {
+        assert_eq!(
+            replay_timed(&session(), 1.0),
+            [
+                Duration::ZERO,
+                Duration::from_millis(100),
+                Duration::from_millis(400)
+            ]
+        );
+    }
+
+    #[test]
+    fn double_speed_replay_takes_half_the_time() {
+        assert_eq!(
+            replay_timed(&session(), 2.0),
+            [
+                Duration::ZERO,
+                Duration::from_millis(50),
+                Duration::from_millis(200)
+            ]
+        );
+    }
+
+    #[test]
+    fn non_positive_speed_replays_without_waiting() {
+        for speed in [0.0, -1.0, f32::NAN] {
+            assert_eq!(replay_timed(&session(), speed), [Duration::ZERO; 3]);
+        }
+    }
+}
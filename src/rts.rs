@@ -0,0 +1,4756 @@
+// the RealTimeStylus backend, which `EasyTablet` is built on
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+#[cfg(feature = "history")]
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+#[cfg(feature = "dispatch-thread")]
+use std::sync::mpsc::{channel, Sender};
+use std::time::{Duration, Instant};
+
+use raw_window_handle::RawWindowHandle;
+use windows::core::{implement, s, w, InParam, Interface, Result, GUID, HRESULT, HSTRING};
+use windows::Win32::Foundation::{
+    BOOL, BSTR, ERROR_SUCCESS, HANDLE, HANDLE_PTR, HWND, LPARAM, POINT, RECT,
+};
+use windows::Win32::Graphics::Gdi::{
+    ClientToScreen, EnumDisplayMonitors, GetDC, GetDeviceCaps, ReleaseDC, ScreenToClient, HDC,
+    HMONITOR, LOGPIXELSX,
+};
+
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_INPROC_SERVER, VARIANT};
+use windows::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+use windows::Win32::System::Ole::VT_I4;
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegOpenKeyExW, HKEY, HKEY_CLASSES_ROOT, KEY_READ,
+};
+use windows::Win32::UI::Controls::{
+    FEEDBACK_GESTURE_PRESSANDTAP, FEEDBACK_PEN_BARRELVISUALIZATION, FEEDBACK_PEN_DOUBLETAP,
+    FEEDBACK_PEN_PRESSANDHOLD, FEEDBACK_PEN_RIGHTTAP, FEEDBACK_PEN_TAP,
+    FEEDBACK_TOUCH_CONTACTVISUALIZATION, FEEDBACK_TOUCH_DOUBLETAP, FEEDBACK_TOUCH_PRESSANDHOLD,
+    FEEDBACK_TOUCH_RIGHTTAP, FEEDBACK_TOUCH_TAP, FEEDBACK_TYPE, POINTER_DEVICE_INFO,
+    POINTER_DEVICE_TYPE_EXTERNAL_PEN, POINTER_DEVICE_TYPE_INTEGRATED_PEN,
+};
+use windows::Win32::UI::TabletPC::{
+    AsyncStylusQueue, IInkDrawingAttributes, IInkTablet, IInkTablet2, IInkTablet3, IRealTimeStylus,
+    ISG_DoubleTap, ISG_Drag, ISG_Flick, ISG_HoldEnter, ISG_HoldLeave, ISG_HoverEnter,
+    ISG_HoverLeave, ISG_RightDrag, ISG_RightTap, ISG_Tap, IStylusAsyncPlugin,
+    IStylusAsyncPlugin_Impl, IStylusPlugin, IStylusPlugin_Impl, InkDrawingAttributes,
+    RTSDI_AllData, RealTimeStylus, RealTimeStylusDataInterest, StylusInfo, TDK_Mouse, TDK_Touch,
+    GUID_PACKETPROPERTY_GUID_ALTITUDE_ORIENTATION, GUID_PACKETPROPERTY_GUID_AZIMUTH_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_HEIGHT, GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE,
+    GUID_PACKETPROPERTY_GUID_PACKET_STATUS, GUID_PACKETPROPERTY_GUID_PITCH_ROTATION,
+    GUID_PACKETPROPERTY_GUID_ROLL_ROTATION, GUID_PACKETPROPERTY_GUID_SERIAL_NUMBER,
+    GUID_PACKETPROPERTY_GUID_TWIST_ORIENTATION, GUID_PACKETPROPERTY_GUID_WIDTH,
+    GUID_PACKETPROPERTY_GUID_X, GUID_PACKETPROPERTY_GUID_X_TILT_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_Y, GUID_PACKETPROPERTY_GUID_YAW_ROTATION,
+    GUID_PACKETPROPERTY_GUID_Y_TILT_ORIENTATION, GUID_PACKETPROPERTY_GUID_Z, PACKET_PROPERTY,
+    PROPERTY_METRICS, SYSTEM_EVENT_DATA, TABLET_DISABLE_FLICKS, TABLET_DISABLE_PRESSANDHOLD,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetAncestor, GetClientRect, GetForegroundWindow, GetMessageTime, GetPropW,
+    GetSystemMetrics, KillTimer, MsgWaitForMultipleObjects, PeekMessageW, RemovePropW, SetPropW,
+    SetTimer, TranslateMessage, GA_ROOT, MSG, NID_EXTERNAL_PEN, NID_INTEGRATED_PEN, NID_READY,
+    PM_REMOVE, QS_ALLINPUT, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_DIGITIZER,
+    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, USER_DEFAULT_SCREEN_DPI, USER_TIMER_MINIMUM,
+};
+
+#[cfg(feature = "strokes")]
+use crate::stroke::{StatsBuilder, Stroke, StrokeRecorder, StrokeStats};
+use crate::win32::{
+    hwnd_from_handle, Contact, ContactKind, CursorState, EventMask, InputSource, PacketStatus,
+    StylusSample, SystemGesture, TipType, WinTabEvent, ERROR_FN,
+};
+#[cfg(feature = "gestures")]
+use crate::DoubleTapConfig;
+#[cfg(feature = "quirks")]
+use crate::Quirk;
+use crate::{
+    __InnerTablet, tilt_to_spherical, Capabilities, CoordinateSpace, EasyTabError, EasyTabOptions,
+    EasyTabResult, EasyTablet, EventResponse, Handedness, ListenerId, Rect, RetryOptions,
+    TabletBackend, TabletBounds, TabletInfo, TabletKind, Warning,
+};
+
+// the packet properties requested from the stylus. properties the tablet doesn't support are left out of the packets.
+const DESIRED_PACKET_PROPERTIES: &[GUID] = &[
+    GUID_PACKETPROPERTY_GUID_X,
+    GUID_PACKETPROPERTY_GUID_Y,
+    GUID_PACKETPROPERTY_GUID_Z,
+    GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE,
+    GUID_PACKETPROPERTY_GUID_X_TILT_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_Y_TILT_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_AZIMUTH_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_ALTITUDE_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_SERIAL_NUMBER,
+    GUID_PACKETPROPERTY_GUID_PACKET_STATUS,
+    GUID_PACKETPROPERTY_GUID_PITCH_ROTATION,
+    GUID_PACKETPROPERTY_GUID_ROLL_ROTATION,
+    GUID_PACKETPROPERTY_GUID_YAW_ROTATION,
+    GUID_PACKETPROPERTY_GUID_TWIST_ORIENTATION,
+    GUID_PACKETPROPERTY_GUID_WIDTH,
+    GUID_PACKETPROPERTY_GUID_HEIGHT,
+];
+
+// a listener registered on the tablet's thread, and the events it wants. the callback is shared so the listeners can
+// be copied out before calling them.
+#[derive(Clone)]
+pub(crate) struct Listener {
+    id: ListenerId,
+    mask: EventMask,
+    priority: i32,
+    cb: Rc<dyn Fn(WinTabEvent) -> EventResponse>,
+}
+
+// where a listener with the given priority goes: after every listener with the same or a higher priority
+fn listener_index(listeners: &[Listener], priority: i32) -> usize {
+    listeners
+        .iter()
+        .position(|listener| listener.priority < priority)
+        .unwrap_or(listeners.len())
+}
+
+// calls the listeners that want an event in order, skipping any that are no longer `registered`. returns
+// `EventResponse::Stop` if a listener stopped the event, otherwise whether any of them handled it.
+fn call_listeners(
+    listeners: &[Listener],
+    event: WinTabEvent,
+    registered: impl Fn(ListenerId) -> bool,
+) -> EventResponse {
+    let mask = event.mask();
+    let mut response = EventResponse::Continue;
+
+    for listener in listeners {
+        if !listener.mask.intersects(mask) || !registered(listener.id) {
+            continue;
+        }
+
+        match (listener.cb)(event) {
+            EventResponse::Continue => {}
+            EventResponse::Handled => response = EventResponse::Handled,
+            EventResponse::Stop => return EventResponse::Stop,
+        }
+    }
+
+    response
+}
+
+// maps a position in tablet coordinates to the one to report, see `EasyTablet::set_coordinate_mapper`
+pub(crate) type CoordinateMapper = Box<dyn Fn(i32, i32) -> (i32, i32)>;
+
+// called with a newly connected tablet, see `EasyTablet::on_connect`
+pub(crate) type ConnectCallback = Box<dyn Fn(&TabletInfo)>;
+
+// rewrites or drops an event before it's handled, see `EasyTablet::set_event_transform`
+pub(crate) type EventTransform = Box<dyn Fn(WinTabEvent) -> Option<WinTabEvent>>;
+
+// called with every raw packet and the index of each property within it, see `EasyTablet::raw_packet_callback`
+#[cfg(feature = "raw-packets")]
+pub(crate) type RawPacketCallback = Box<dyn Fn(&[i32], &HashMap<GUID, usize>)>;
+
+// known device quirks, keyed by a fragment of the tablet name (as reported by `IInkTablet::Name`).
+// only add devices whose misbehaviour has been confirmed on real hardware.
+#[cfg(feature = "quirks")]
+const QUIRKS: &[(&str, Quirk)] = &[
+    // the windows ink driver reports the y tilt leaning away from the user as positive
+    ("XP-Pen", Quirk::InvertedTiltY),
+    // reports a maximum of 8191, but the pen tops out around 8000
+    ("Huion", Quirk::PressureMax(8000)),
+];
+
+// the quirks of the tablet with the given name
+#[cfg(feature = "quirks")]
+fn quirks_for(name: &str) -> Vec<Quirk> {
+    QUIRKS
+        .iter()
+        .filter(|(fragment, _)| name.contains(fragment))
+        .map(|(_, quirk)| *quirk)
+        .collect()
+}
+
+// the layout of the packets sent by a tablet context.
+// each packet is a run of `i32`s, one for each property, in the order given here.
+pub(crate) struct PacketDescription {
+    properties: Vec<PACKET_PROPERTY>,
+    // the index of each property within a packet
+    offsets: HashMap<GUID, usize>,
+    // the workarounds applied to the tablet's packets
+    #[cfg(feature = "quirks")]
+    quirks: Vec<Quirk>,
+}
+
+impl PacketDescription {
+    // reads the packet layout for the given tablet context
+    fn read(stylus: &IRealTimeStylus, tcid: u32) -> Result<Self> {
+        let mut scale_x = 0.0f32;
+        let mut scale_y = 0.0f32;
+        let mut count = 0u32;
+        let mut raw: *mut PACKET_PROPERTY = std::ptr::null_mut();
+
+        unsafe {
+            stylus.GetPacketDescriptionData(
+                tcid,
+                &mut scale_x,
+                &mut scale_y,
+                &mut count,
+                &mut raw,
+            )?
+        };
+
+        // the array is allocated by the stylus, so copy it out and give the memory back
+        let properties = unsafe { std::slice::from_raw_parts(raw, count as usize) }.to_vec();
+        unsafe { CoTaskMemFree(raw as *const _) };
+
+        Ok(Self {
+            offsets: properties
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p.guid, i))
+                .collect(),
+            properties,
+            #[cfg(feature = "quirks")]
+            quirks: Vec::new(),
+        })
+    }
+
+    // the number of values in a single packet
+    fn stride(&self) -> usize {
+        self.properties.len()
+    }
+
+    // the index of the property within a packet, if the tablet reports it
+    fn offset(&self, guid: &GUID) -> Option<usize> {
+        self.offsets.get(guid).copied()
+    }
+
+    fn metrics(&self, guid: &GUID) -> Option<&PROPERTY_METRICS> {
+        self.properties
+            .iter()
+            .find(|p| &p.guid == guid)
+            .map(|p| &p.PropertyMetrics)
+    }
+
+    // the range of the x and y properties
+    fn bounds(&self) -> Option<TabletBounds> {
+        let x = self.metrics(&GUID_PACKETPROPERTY_GUID_X)?;
+        let y = self.metrics(&GUID_PACKETPROPERTY_GUID_Y)?;
+
+        Some(TabletBounds {
+            min_x: x.nLogicalMin,
+            min_y: y.nLogicalMin,
+            max_x: x.nLogicalMax,
+            max_y: y.nLogicalMax,
+        })
+    }
+
+    // reads a property from a packet, if the tablet reports it
+    fn value(&self, packet: &[i32], guid: &GUID) -> Option<i32> {
+        self.offset(guid).map(|i| packet[i])
+    }
+
+    // reads an angle property from a packet in degrees, if the tablet reports it
+    fn angle(&self, packet: &[i32], guid: &GUID) -> Option<f32> {
+        let value = self.value(packet, guid)? as f32;
+
+        // the resolution is the number of values per degree
+        match self.metrics(guid) {
+            Some(m) if m.fResolution > 0.0 => Some(value / m.fResolution),
+            _ => Some(value),
+        }
+    }
+
+    // decodes a single packet
+    fn sample(&self, packet: &[i32]) -> StylusSample {
+        let pressure = match (
+            self.value(packet, &GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE),
+            self.metrics(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE),
+        ) {
+            (Some(p), Some(m)) if m.nLogicalMax > m.nLogicalMin => {
+                (p - m.nLogicalMin) as f32 / (m.nLogicalMax - m.nLogicalMin) as f32
+            }
+            _ => 0.0,
+        };
+
+        let tilt = self
+            .angle(packet, &GUID_PACKETPROPERTY_GUID_X_TILT_ORIENTATION)
+            .zip(self.angle(packet, &GUID_PACKETPROPERTY_GUID_Y_TILT_ORIENTATION));
+
+        let (azimuth, altitude) = match (
+            self.angle(packet, &GUID_PACKETPROPERTY_GUID_AZIMUTH_ORIENTATION),
+            self.angle(packet, &GUID_PACKETPROPERTY_GUID_ALTITUDE_ORIENTATION),
+        ) {
+            (Some(azimuth), Some(altitude)) => (Some(azimuth), Some(altitude)),
+            _ => tilt.map(|(x, y)| tilt_to_spherical(x, y)).unzip(),
+        };
+
+        StylusSample {
+            x: self
+                .value(packet, &GUID_PACKETPROPERTY_GUID_X)
+                .unwrap_or_default(),
+            y: self
+                .value(packet, &GUID_PACKETPROPERTY_GUID_Y)
+                .unwrap_or_default(),
+            z: self.value(packet, &GUID_PACKETPROPERTY_GUID_Z),
+            pressure,
+            tilt,
+            azimuth,
+            altitude,
+            pitch: self.angle(packet, &GUID_PACKETPROPERTY_GUID_PITCH_ROTATION),
+            roll: self.angle(packet, &GUID_PACKETPROPERTY_GUID_ROLL_ROTATION),
+            twist: self.angle(packet, &GUID_PACKETPROPERTY_GUID_TWIST_ORIENTATION),
+            yaw: self.angle(packet, &GUID_PACKETPROPERTY_GUID_YAW_ROTATION),
+            serial: self.value(packet, &GUID_PACKETPROPERTY_GUID_SERIAL_NUMBER),
+            status: self
+                .value(packet, &GUID_PACKETPROPERTY_GUID_PACKET_STATUS)
+                .map(PacketStatus::from_bits_truncate),
+            input_source: InputSource::Pen,
+            instant: None,
+            os_time: None,
+            contact_size: self
+                .value(packet, &GUID_PACKETPROPERTY_GUID_WIDTH)
+                .zip(self.value(packet, &GUID_PACKETPROPERTY_GUID_HEIGHT)),
+            // the packet doesn't say, the caller fills it in
+            cursor: 0,
+        }
+    }
+}
+
+// the screen-space rects of every monitor, in the order the OS enumerates them
+fn monitor_rects() -> Vec<Rect> {
+    unsafe extern "system" fn push_monitor(
+        _: HMONITOR,
+        _: HDC,
+        rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let rects = &mut *(data.0 as *mut Vec<Rect>);
+        rects.push((*rect).into());
+
+        true.into()
+    }
+
+    let mut rects = Vec::new();
+
+    unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            std::ptr::null(),
+            Some(push_monitor),
+            LPARAM(&mut rects as *mut Vec<Rect> as isize),
+        )
+    };
+
+    rects
+}
+
+// guesses whether a tablet is part of a display from whether its shape matches one of the monitors
+fn kind_from_bounds(bounds: &TabletBounds, monitors: &[Rect]) -> TabletKind {
+    if bounds.width() <= 0 || bounds.height() <= 0 || monitors.is_empty() {
+        return TabletKind::Unknown;
+    }
+
+    let aspect = bounds.width() as f32 / bounds.height() as f32;
+
+    let matches = monitors
+        .iter()
+        .filter(|m| m.width() > 0 && m.height() > 0)
+        .any(|m| ((m.width() as f32 / m.height() as f32) / aspect - 1.0).abs() < 0.01);
+
+    if matches {
+        TabletKind::Integrated
+    } else {
+        TabletKind::External
+    }
+}
+
+// the screen-space rect covering every monitor
+fn virtual_screen_rect() -> Rect {
+    unsafe {
+        let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+
+        Rect {
+            left,
+            top,
+            right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+            bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+        }
+    }
+}
+
+// the number of events kept in the queue. the oldest events are dropped first.
+const QUEUE_CAPACITY: usize = 256;
+
+// the number of samples kept in the history
+#[cfg(feature = "history")]
+const HISTORY_CAPACITY: usize = 64;
+
+// how far before the oldest, or after the newest, sample `position_at` is willing to guess
+#[cfg(feature = "history")]
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(50);
+
+// carries on from the newest two samples at the same velocity, see `EasyTablet::predicted_next`
+#[cfg(feature = "history")]
+fn predicted_next(history: &VecDeque<(Instant, StylusSample)>) -> Option<(f32, f32)> {
+    let mut newest = history.iter().rev();
+    let (_, b) = newest.next()?;
+    let (_, a) = newest.next()?;
+
+    Some((
+        b.x as f32 + (b.x - a.x) as f32,
+        b.y as f32 + (b.y - a.y) as f32,
+    ))
+}
+
+// estimates the position at `t` from the samples either side of it, see `EasyTablet::position_at`
+#[cfg(feature = "history")]
+fn position_at(history: &VecDeque<(Instant, StylusSample)>, t: Instant) -> Option<(f32, f32)> {
+    let (first_t, first) = *history.front()?;
+    let (last_t, _) = *history.back()?;
+
+    if t + MAX_EXTRAPOLATION < first_t || t > last_t + MAX_EXTRAPOLATION {
+        return None;
+    }
+
+    if history.len() == 1 {
+        return Some((first.x as f32, first.y as f32));
+    }
+
+    // the first sample at or after `t`, paired with the one before it
+    let i = history
+        .iter()
+        .position(|(st, _)| *st >= t)
+        .unwrap_or(history.len() - 1)
+        .max(1);
+
+    let (t0, a) = history[i - 1];
+    let (t1, b) = history[i];
+
+    let span = t1.duration_since(t0).as_secs_f32();
+
+    // packets arrive in batches, so neighbouring samples can share a timestamp
+    if span == 0.0 {
+        return Some((b.x as f32, b.y as f32));
+    }
+
+    let offset = if t >= t0 {
+        t.duration_since(t0).as_secs_f32()
+    } else {
+        -t0.duration_since(t).as_secs_f32()
+    };
+    let f = offset / span;
+
+    Some((
+        a.x as f32 + (b.x - a.x) as f32 * f,
+        a.y as f32 + (b.y - a.y) as f32 * f,
+    ))
+}
+
+// the kinds of events a tablet with the given capabilities produces, see `EasyTablet::supported_events`
+fn supported_events(capabilities: Capabilities, opts: &EasyTabOptions) -> EventMask {
+    let mut events = EventMask::CONTACT
+        | EventMask::MOVE
+        | EventMask::HOVER
+        | EventMask::STATUS
+        | EventMask::WINDOW
+        | EventMask::GESTURES;
+
+    events.set(
+        EventMask::PRESSURE,
+        capabilities.pressure || opts.synth_pressure,
+    );
+    events.set(EventMask::BUTTONS, capabilities.buttons > 0);
+    events.set(EventMask::RELATIVE, opts.relative_mode);
+
+    events
+}
+
+// a listener on the dispatcher thread
+#[cfg(feature = "dispatch-thread")]
+type ThreadedListener = Box<dyn Fn(WinTabEvent) + Send>;
+
+// a message to the dispatcher thread
+#[cfg(feature = "dispatch-thread")]
+pub(crate) enum DispatcherMessage {
+    Event(WinTabEvent),
+    AddListener(ListenerId, ThreadedListener),
+    RemoveListener(ListenerId),
+}
+
+// starts the thread that runs the threaded listeners. it stops once the tablet is dropped.
+#[cfg(feature = "dispatch-thread")]
+fn spawn_dispatcher() -> Sender<DispatcherMessage> {
+    let (tx, rx) = channel();
+
+    std::thread::spawn(move || {
+        let mut listeners: Vec<(ListenerId, ThreadedListener)> = Vec::new();
+
+        for message in rx {
+            match message {
+                DispatcherMessage::Event(event) => {
+                    for (_, listener) in listeners.iter() {
+                        listener(event);
+                    }
+                }
+                DispatcherMessage::AddListener(id, cb) => listeners.push((id, cb)),
+                DispatcherMessage::RemoveListener(id) => listeners.retain(|(lid, _)| *lid != id),
+            }
+        }
+    });
+
+    tx
+}
+
+// blocks until a message arrives on this thread's queue, or the timeout elapses
+fn wait_for_messages(timeout: Duration) {
+    let ms = timeout.as_millis().min(u32::MAX as u128) as u32;
+
+    unsafe { MsgWaitForMultipleObjects(&[], false, ms, QS_ALLINPUT) };
+}
+
+type GetPointerDevices = unsafe extern "system" fn(*mut u32, *mut POINTER_DEVICE_INFO) -> BOOL;
+type GetPointerDeviceRects = unsafe extern "system" fn(HANDLE, *mut RECT, *mut RECT) -> BOOL;
+
+// the screen-space rect windows maps a pen digitiser onto, preferring the one with the given name when there are
+// several. `None` on versions before windows 8, or when there is no pen digitiser.
+fn os_pen_mapping(name: Option<&str>) -> Option<Rect> {
+    // loaded at runtime so the crate still starts on windows 7
+    let (devices, rects) = unsafe {
+        let user32 = GetModuleHandleW(w!("user32.dll")).ok()?;
+
+        (
+            std::mem::transmute::<unsafe extern "system" fn() -> isize, GetPointerDevices>(
+                GetProcAddress(user32, s!("GetPointerDevices"))?,
+            ),
+            std::mem::transmute::<unsafe extern "system" fn() -> isize, GetPointerDeviceRects>(
+                GetProcAddress(user32, s!("GetPointerDeviceRects"))?,
+            ),
+        )
+    };
+
+    let mut count = 0;
+
+    if !unsafe { devices(&mut count, std::ptr::null_mut()) }.as_bool() || count == 0 {
+        return None;
+    }
+
+    let mut infos = vec![unsafe { std::mem::zeroed::<POINTER_DEVICE_INFO>() }; count as usize];
+
+    if !unsafe { devices(&mut count, infos.as_mut_ptr()) }.as_bool() {
+        return None;
+    }
+
+    let pens: Vec<_> = infos[..count as usize]
+        .iter()
+        .filter(|info| {
+            info.pointerDeviceType == POINTER_DEVICE_TYPE_INTEGRATED_PEN
+                || info.pointerDeviceType == POINTER_DEVICE_TYPE_EXTERNAL_PEN
+        })
+        .collect();
+
+    let product = |info: &POINTER_DEVICE_INFO| {
+        let len = info
+            .productString
+            .iter()
+            .position(|c| *c == 0)
+            .unwrap_or(info.productString.len());
+
+        String::from_utf16_lossy(&info.productString[..len])
+    };
+
+    let pen = name
+        .and_then(|name| pens.iter().find(|info| product(info) == name))
+        .or_else(|| pens.first())?;
+
+    let (mut device_rect, mut display_rect) = (RECT::default(), RECT::default());
+
+    if !unsafe { rects(pen.device, &mut device_rect, &mut display_rect) }.as_bool() {
+        return None;
+    }
+
+    Some(display_rect.into())
+}
+
+type SetWindowFeedbackSetting =
+    unsafe extern "system" fn(HWND, FEEDBACK_TYPE, u32, u32, *const std::ffi::c_void) -> BOOL;
+
+// turns off the visual feedback windows draws for taps and contacts on a window. returns false on versions before
+// windows 8, which don't have the setting.
+fn suppress_touch_feedback(hwnd: HWND) -> bool {
+    // loaded at runtime so the crate still starts on windows 7
+    let set = unsafe {
+        GetModuleHandleW(w!("user32.dll"))
+            .ok()
+            .and_then(|user32| GetProcAddress(user32, s!("SetWindowFeedbackSetting")))
+    };
+
+    let set = match set {
+        Some(f) => unsafe {
+            std::mem::transmute::<unsafe extern "system" fn() -> isize, SetWindowFeedbackSetting>(f)
+        },
+        None => return false,
+    };
+
+    let disabled = BOOL::from(false);
+
+    [
+        FEEDBACK_TOUCH_CONTACTVISUALIZATION,
+        FEEDBACK_PEN_BARRELVISUALIZATION,
+        FEEDBACK_PEN_TAP,
+        FEEDBACK_PEN_DOUBLETAP,
+        FEEDBACK_PEN_PRESSANDHOLD,
+        FEEDBACK_PEN_RIGHTTAP,
+        FEEDBACK_TOUCH_TAP,
+        FEEDBACK_TOUCH_DOUBLETAP,
+        FEEDBACK_TOUCH_PRESSANDHOLD,
+        FEEDBACK_TOUCH_RIGHTTAP,
+        FEEDBACK_GESTURE_PRESSANDTAP,
+    ]
+    .into_iter()
+    .all(|feedback| unsafe {
+        set(
+            hwnd,
+            feedback,
+            0,
+            std::mem::size_of::<BOOL>() as u32,
+            &disabled as *const _ as *const _,
+        )
+        .as_bool()
+    })
+}
+
+// the system gesture for a `SystemEvent`, or `None` for events that aren't gestures
+fn system_gesture(event: u16) -> Option<SystemGesture> {
+    let gestures = [
+        (ISG_Tap, SystemGesture::Tap),
+        (ISG_DoubleTap, SystemGesture::DoubleTap),
+        (ISG_RightTap, SystemGesture::RightTap),
+        (ISG_Drag, SystemGesture::Drag),
+        (ISG_RightDrag, SystemGesture::RightDrag),
+        (ISG_HoldEnter, SystemGesture::HoldEnter),
+        (ISG_HoldLeave, SystemGesture::HoldLeave),
+        (ISG_HoverEnter, SystemGesture::HoverEnter),
+        (ISG_HoverLeave, SystemGesture::HoverLeave),
+        (ISG_Flick, SystemGesture::Flick),
+    ];
+
+    gestures
+        .into_iter()
+        .find(|(isg, _)| isg.0 == event as i32)
+        .map(|(_, gesture)| gesture)
+}
+
+// the flags of the tablet service's window property that turn off the action windows ties to a system gesture, once
+// a listener has handled it. 0 when the gesture wasn't handled, or has no action that can be turned off.
+fn gesture_suppression(gesture: SystemGesture, response: EventResponse) -> u32 {
+    if response == EventResponse::Continue {
+        return 0;
+    }
+
+    match gesture {
+        SystemGesture::HoldEnter => TABLET_DISABLE_PRESSANDHOLD,
+        SystemGesture::Flick => TABLET_DISABLE_FLICKS,
+        _ => 0,
+    }
+}
+
+// sets or clears flags of the property the tablet service reads a window's gesture settings from
+fn set_tablet_service_flags(hwnd: HWND, flags: u32, on: bool) -> bool {
+    let current = unsafe { GetPropW(hwnd, w!("MicrosoftTabletPenServiceProperty")) }.0 as u32;
+    let flags = if on {
+        current | flags
+    } else {
+        current & !flags
+    };
+
+    if flags == 0 {
+        return unsafe { RemovePropW(hwnd, w!("MicrosoftTabletPenServiceProperty")) }.is_ok();
+    }
+
+    unsafe {
+        SetPropW(
+            hwnd,
+            w!("MicrosoftTabletPenServiceProperty"),
+            HANDLE(flags as isize),
+        )
+    }
+    .as_bool()
+}
+
+type GetDpiForWindow = unsafe extern "system" fn(HWND) -> u32;
+
+// the dpi of a window. falls back to the dpi of the screen on versions before windows 10, which don't scale windows
+// individually.
+fn window_dpi(hwnd: HWND) -> u32 {
+    // loaded at runtime so the crate still starts on windows 7
+    let get = unsafe {
+        GetModuleHandleW(w!("user32.dll"))
+            .ok()
+            .and_then(|user32| GetProcAddress(user32, s!("GetDpiForWindow")))
+    };
+
+    let dpi = match get {
+        Some(f) => unsafe {
+            std::mem::transmute::<unsafe extern "system" fn() -> isize, GetDpiForWindow>(f)(hwnd)
+        },
+        None => unsafe {
+            let hdc = GetDC(hwnd);
+            let dpi = GetDeviceCaps(hdc, LOGPIXELSX);
+            ReleaseDC(hwnd, hdc);
+
+            dpi.max(0) as u32
+        },
+    };
+
+    // zero means the window handle isn't valid
+    if dpi == 0 {
+        USER_DEFAULT_SCREEN_DPI
+    } else {
+        dpi
+    }
+}
+
+// the time of the message being handled on this thread. the stylus notifications are delivered through messages, so
+// this is when they were posted.
+fn message_time() -> u32 {
+    // the time is a tick count that wraps, so it's really unsigned
+    unsafe { GetMessageTime() as u32 }
+}
+
+// dispatches every message waiting on this thread's queue. the stylus delivers its notifications through these.
+fn pump_messages() {
+    let mut msg = MSG::default();
+
+    unsafe {
+        while PeekMessageW(&mut msg, HWND::default(), 0, 0, PM_REMOVE).as_bool() {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// the longest a button can be held while hovering for the click to count as an air tap
+#[cfg(feature = "gestures")]
+const AIR_TAP_INTERVAL: Duration = Duration::from_millis(300);
+
+// whether a tap, or the gap between two taps, is short and close enough to be part of a double tap. both ends are a
+// time and position.
+#[cfg(feature = "gestures")]
+fn within_tap(
+    config: &DoubleTapConfig,
+    (start, sx, sy): (Instant, i32, i32),
+    (end, ex, ey): (Instant, i32, i32),
+) -> bool {
+    end.duration_since(start) <= config.interval
+        && ((sx - ex) as f32).hypot((sy - ey) as f32) <= config.max_distance as f32
+}
+
+// whether a contact that lifted at `lifted` and goes back down at `now` is still within the debounce window, and so
+// carries on as if it never lifted
+fn within_debounce(debounce: Option<Duration>, lifted: Instant, now: Instant) -> bool {
+    debounce.is_some_and(|debounce| now.saturating_duration_since(lifted) <= debounce)
+}
+
+// whether a move at `now` is far enough after the last one let through to stay under `hz` events a second
+#[cfg(feature = "filters")]
+fn under_rate_cap(hz: u32, last: Option<Instant>, now: Instant) -> bool {
+    match last {
+        Some(last) => now.saturating_duration_since(last) >= Duration::from_secs(1) / hz,
+        None => true,
+    }
+}
+
+// how far a cursor moved since the last sample (`origin`, with its cursor id). there's no delta for the first sample
+// after the origin was reset, or across a change of cursor, or when the cursor didn't move.
+fn relative_delta(
+    origin: Option<(u32, i32, i32)>,
+    (cid, x, y): (u32, i32, i32),
+) -> Option<(i32, i32)> {
+    let (_, ox, oy) = origin.filter(|(origin_cid, ..)| *origin_cid == cid)?;
+
+    Some((x - ox, y - oy)).filter(|&delta| delta != (0, 0))
+}
+
+// treats pressure under the deadzone as none at all
+#[cfg(feature = "filters")]
+fn apply_deadzone(pressure: f32, deadzone: f32) -> f32 {
+    if pressure < deadzone {
+        0.0
+    } else {
+        pressure
+    }
+}
+
+// whether the pressure crossed the threshold, given whether it was above it: `Some(true)` when it rose past it,
+// `Some(false)` when it fell back under it less the hysteresis
+#[cfg(feature = "filters")]
+fn threshold_crossing(above: bool, pressure: f32, threshold: f32, hysteresis: f32) -> Option<bool> {
+    if !above && pressure > threshold {
+        Some(true)
+    } else if above && pressure < threshold - hysteresis {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// normalised pressure at or above this counts as the maximum. some pens never quite report their full range.
+const MAX_PRESSURE: f32 = 0.99;
+
+// whether a sample bottoms out the pen for the first time in the stroke. `reached` is set once it has, and cleared
+// when the next contact goes down.
+fn bottomed_out(reached: &Cell<bool>, pressure: f32) -> bool {
+    pressure >= MAX_PRESSURE && !reached.replace(true)
+}
+
+// the resolution pressure samples are recorded at. `pressure_histogram` merges these into the requested buckets.
+#[cfg(feature = "histogram")]
+pub(crate) const HISTOGRAM_BINS: usize = 256;
+
+// merges the recorded bins into the given number of buckets. when they don't divide evenly, the buckets get a
+// different number of bins each.
+#[cfg(feature = "histogram")]
+fn merge_histogram(bins: &[u32; HISTOGRAM_BINS], buckets: usize) -> Vec<u32> {
+    let mut histogram = vec![0; buckets];
+
+    if buckets == 0 {
+        return histogram;
+    }
+
+    for (bin, count) in bins.iter().enumerate() {
+        histogram[bin * buckets / HISTOGRAM_BINS] += count;
+    }
+
+    histogram
+}
+
+// a batch of packets copied out of a stylus callback, waiting to be decoded (with `EasyTabOptions::lazy_decode`)
+pub(crate) struct PendingPackets {
+    info: StylusInfo,
+    packets: Vec<i32>,
+    event: fn(StylusSample) -> WinTabEvent,
+    // when the packets arrived, and the message time then
+    received: (Instant, u32),
+}
+
+// custom stylus data queued behind the pending packets by `flush`, to know when they've all been delivered
+const FLUSH_MARKER: GUID = GUID::from_u128(0x3f0c4a1e_9b2d_4c67_8e51_d27a6b0f93c4);
+
+// how long `flush` waits for the stylus to catch up
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+// a retry waiting on a timer: the tablet, the tablet being read and how many retries have been made already
+type PendingRetry = (Weak<__InnerTablet>, IInkTablet, u32);
+
+thread_local! {
+    // the tablets whose contacts are being watched for `opts.stroke_timeout`, keyed by the id of the timer doing it
+    static EXPIRY_TIMERS: RefCell<HashMap<usize, Weak<__InnerTablet>>> = RefCell::default();
+
+    // the tablets waiting to retry reading a newly connected tablet, keyed by the id of the timer for the retry
+    static RETRY_TIMERS: RefCell<HashMap<usize, PendingRetry>> = RefCell::default();
+}
+
+// the delay before the given retry (counting from `0`) of reading a newly connected tablet, or `None` once every
+// retry has been used up. each delay is `backoff_factor` times the one before, up to `max_delay`.
+fn retry_delay(retry: &RetryOptions, retries: u32) -> Option<Duration> {
+    if retries >= retry.max_retries {
+        return None;
+    }
+
+    Some((0..retries).fold(retry.initial_delay, |delay, _| {
+        delay
+            .saturating_mul(retry.backoff_factor)
+            .min(retry.max_delay)
+    }))
+}
+
+// reads the layout of a newly connected tablet, scheduling a retry if it fails. `retries` is how many retries have
+// been made already.
+fn reinitialise(tablet: &Rc<__InnerTablet>, ink_tablet: &IInkTablet, retries: u32) -> Result<()> {
+    match tablet.read_tablet(ink_tablet) {
+        Ok(()) => tablet.handle_event(WinTabEvent::TabletChanged),
+        Err(e) => schedule_retry(tablet, ink_tablet, retries, e.code()),
+    }
+}
+
+// starts a timer for the next retry of reading a newly connected tablet, replacing one already waiting, or reports the
+// error once every retry has been used up. the retries wait on a timer rather than sleeping, as the tablet is connected
+// from a stylus callback, which would hold up the stylus (and the window's message loop) while waiting.
+fn schedule_retry(
+    tablet: &Rc<__InnerTablet>,
+    ink_tablet: &IInkTablet,
+    retries: u32,
+    error: HRESULT,
+) -> Result<()> {
+    tablet.stop_retry_timer();
+
+    let delay = match retry_delay(&tablet.opts.retry, retries) {
+        Some(delay) => delay,
+        None => return tablet.handle_event(WinTabEvent::Error(error)),
+    };
+
+    let ms = delay
+        .as_millis()
+        .clamp(USER_TIMER_MINIMUM as u128, u32::MAX as u128) as u32;
+    let id = unsafe { SetTimer(HWND::default(), 0, ms, Some(retry_timer_proc)) };
+
+    // without a timer there's no way to retry later
+    if id == 0 {
+        return tablet.handle_event(WinTabEvent::Error(error));
+    }
+
+    tablet.retry_timer.set(id);
+    RETRY_TIMERS.with(|timers| {
+        timers
+            .borrow_mut()
+            .insert(id, (Rc::downgrade(tablet), ink_tablet.clone(), retries))
+    });
+
+    Ok(())
+}
+
+unsafe extern "system" fn retry_timer_proc(_: HWND, _: u32, id: usize, _: u32) {
+    // the timer would fire again, but each retry is only made once
+    KillTimer(HWND::default(), id);
+
+    let retry = RETRY_TIMERS.with(|timers| timers.borrow_mut().remove(&id));
+
+    let (tablet, ink_tablet, retries) = match retry {
+        Some((tablet, ink_tablet, retries)) => match tablet.upgrade() {
+            Some(tablet) => (tablet, ink_tablet, retries),
+            None => return,
+        },
+        None => return,
+    };
+
+    tablet.retry_timer.set(0);
+
+    if let Err(e) = reinitialise(&tablet, &ink_tablet, retries + 1) {
+        let _ = tablet.handle_event(WinTabEvent::Error(e.code()));
+    }
+}
+
+// starts a timer lifting the tablet's contacts once they have been quiet for longer than `opts.stroke_timeout`, unless
+// one is running already. a tablet that drops the contact usually stops sending packets too, so packets arriving can't
+// be relied on to check it. the timer stops itself once every contact has lifted.
+fn watch_contacts(tablet: &Rc<__InnerTablet>) {
+    let timeout = match tablet.opts.stroke_timeout {
+        Some(timeout) => timeout,
+        None => return,
+    };
+
+    if tablet.expiry_timer.get() != 0 || tablet.contact_seen.borrow().is_empty() {
+        return;
+    }
+
+    // checking twice per timeout lifts a contact at most half a timeout late
+    let ms = (timeout.as_millis() / 2).clamp(USER_TIMER_MINIMUM as u128, u32::MAX as u128) as u32;
+    let id = unsafe { SetTimer(HWND::default(), 0, ms, Some(expiry_timer_proc)) };
+
+    // without a timer the contacts are still checked whenever packets arrive
+    if id != 0 {
+        tablet.expiry_timer.set(id);
+        EXPIRY_TIMERS.with(|timers| timers.borrow_mut().insert(id, Rc::downgrade(tablet)));
+    }
+}
+
+unsafe extern "system" fn expiry_timer_proc(_: HWND, _: u32, id: usize, _: u32) {
+    let tablet = EXPIRY_TIMERS.with(|timers| timers.borrow().get(&id).and_then(Weak::upgrade));
+
+    let tablet = match tablet {
+        Some(tablet) => tablet,
+        None => {
+            KillTimer(HWND::default(), id);
+            return;
+        }
+    };
+
+    if let Err(e) = tablet.expire_contacts() {
+        let _ = tablet.handle_event(WinTabEvent::Error(e.code()));
+    }
+
+    if tablet.contact_seen.borrow().is_empty() {
+        tablet.stop_expiry_timer();
+    }
+}
+
+// the stylus' async plugin collection, so the plugin can be inserted into something other than the COM object
+trait AsyncPlugins<P> {
+    fn insert_async_plugin(&self, index: u32, plugin: &P) -> Result<()>;
+}
+
+impl AsyncPlugins<IStylusAsyncPlugin> for IRealTimeStylus {
+    fn insert_async_plugin(&self, index: u32, plugin: &IStylusAsyncPlugin) -> Result<()> {
+        unsafe { self.AddStylusAsyncPlugin(index, plugin) }
+    }
+}
+
+// adds the plugin to the front of the stylus' async plugin collection, so packets it consumes (see
+// `EventResponse::Handled`) never reach the other plugins. unlike an index read from the plugin count, the front can't
+// go stale when another plugin is added to a shared stylus in between.
+fn add_async_plugin<P>(stylus: &impl AsyncPlugins<P>, plugin: &P) -> Result<()> {
+    stylus.insert_async_plugin(0, plugin)
+}
+
+// creates a `VT_I4` variant, used to index into ink collections
+fn variant_i4(value: i32) -> VARIANT {
+    let mut variant = VARIANT::default();
+
+    unsafe {
+        let inner = &mut *variant.Anonymous.Anonymous;
+        inner.vt = VT_I4.0 as u16;
+        inner.Anonymous.lVal = value;
+    }
+
+    variant
+}
+
+// replaces the packets of a `Packets` or `InAirPackets` call with none when a listener handled them, so the plugins
+// after this one don't receive them. the stylus only reads the count when the buffer is set, so both are written.
+// returns whether the packets were consumed.
+unsafe fn consume_packets(
+    response: EventResponse,
+    packets: *const i32,
+    count: *mut u32,
+    buffer: *mut *mut i32,
+) -> bool {
+    if response == EventResponse::Continue || count.is_null() || buffer.is_null() {
+        return false;
+    }
+
+    *count = 0;
+    *buffer = packets as *mut i32;
+
+    true
+}
+
+// the values of a `VARIANT_BOOL`, which the ink properties use
+const VARIANT_TRUE: i16 = -1;
+const VARIANT_FALSE: i16 = 0;
+
+// the drawing attribute that driver smoothing is stored in, so it can be set on something other than the COM object
+trait FitToCurve {
+    fn set_fit_to_curve(&self, flag: i16) -> Result<()>;
+}
+
+impl FitToCurve for IInkDrawingAttributes {
+    fn set_fit_to_curve(&self, flag: i16) -> Result<()> {
+        unsafe { self.SetFitToCurve(flag) }
+    }
+}
+
+// turns driver smoothing on or off for the given drawing attributes
+fn apply_driver_smoothing(attributes: &impl FitToCurve, on: bool) -> Result<()> {
+    attributes.set_fit_to_curve(if on { VARIANT_TRUE } else { VARIANT_FALSE })
+}
+
+// parses a guid in the registry format (`{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}`), braces optional
+fn parse_guid(s: &str) -> EasyTabResult<GUID> {
+    let trimmed = s.trim_start_matches('{').trim_end_matches('}');
+    let hex: String = trimmed.chars().filter(|c| *c != '-').collect();
+
+    if trimmed.len() != 36 || hex.len() != 32 {
+        return Err(EasyTabError::InvalidGuid(s.to_owned()));
+    }
+
+    u128::from_str_radix(&hex, 16)
+        .map(GUID::from_u128)
+        .map_err(|_| EasyTabError::InvalidGuid(s.to_owned()))
+}
+
+impl EasyTablet {
+    /// Initialises a tablet.
+    ///
+    /// ## Arguments
+    ///
+    /// - `hwnd`: `HANDLE_PTR` - a handle to a window to bind the tablet to.
+    ///
+    /// <br>
+    ///
+    /// **Note**: This functions assumes that [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex) has previously been called.
+    /// - Refer to [`init_options`] for more info.
+
+    // pub fn init(hwnd: HANDLE_PTR) -> EasyTabResult<Self> {
+    //     EasyTablet::init_options(hwnd, EasyTabOptions::default())
+    // }
+
+    pub fn init<W: Into<usize>>(hwnd: W) -> EasyTabResult<Self> {
+        EasyTablet::init_options(HANDLE_PTR(hwnd.into()), EasyTabOptions::default())
+    }
+
+    /// Initialises a tablet bound to the window behind a [`RawWindowHandle`] (e.g. from winit).
+    ///
+    /// Fails with [`EasyTabError::UnsupportedHandle`] if the handle isn't a Win32 one.
+    pub fn init_from_handle(handle: RawWindowHandle) -> EasyTabResult<Self> {
+        EasyTablet::init_options(hwnd_from_handle(handle)?, EasyTabOptions::default())
+    }
+
+    /// Initialises a tablet bound to the window that is in the foreground at the time of the call.
+    ///
+    /// Useful for tools (such as annotation utilities) that attach to whatever app the user is currently in.
+    ///
+    /// <br>
+    ///
+    /// **Note**: The foreground window can change at any moment, including between the user's last interaction and this
+    /// call. The tablet stays bound to the window that was in the foreground when it was initialised.
+    /// - Refer to [`init`] for more info.
+    pub fn init_foreground() -> EasyTabResult<Self> {
+        let hwnd = unsafe { GetForegroundWindow() };
+
+        if hwnd.0 == 0 {
+            return Err(EasyTabError::NoForegroundWindow);
+        }
+
+        EasyTablet::init(hwnd.0 as usize)
+    }
+
+    /// Initialises a tablet with the given options.
+    ///
+    /// ## Arguments
+    ///
+    /// - `hwnd`: `HANDLE_PTR`&emsp;&emsp;- a handle to a window to bind the tablet to.
+    /// - `opts`: `WinTabOptions` - the initialisation options for the tablet.
+    ///
+    /// <br>
+    ///
+    /// **Note**: This functions assumes that [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex) has previously been called.
+    ///
+    /// ```
+    /// // before calling `init_options`
+    /// unsafe {
+    ///     CoInitializeEx(
+    ///         std::ptr::null(),
+    ///         COINIT_APARTMENTTHREADED,
+    ///     )
+    ///     .expect("failed to initalise COM");
+    /// }
+    /// //...
+    /// let tablet = EasyTablet::init_options(...)?;
+    /// ```
+    pub fn init_options(hwnd: HANDLE_PTR, opts: EasyTabOptions) -> EasyTabResult<Self> {
+        let auto_enable = opts.auto_enable;
+
+        let slf = Self::disconnected_options(opts)?;
+        slf.connect(hwnd)?;
+
+        if auto_enable {
+            slf.enable()?;
+        }
+
+        Ok(slf)
+    }
+
+    /// Returns whether tablet input is available on this system: the `RealTimeStylus` COM class is registered and a
+    /// digitiser is ready.
+    ///
+    /// Unlike [`init_options`](Self::init_options), this doesn't create anything, so COM doesn't have to be
+    /// initialised. Apps can use it to hide tablet features on machines that can't support them.
+    #[must_use]
+    pub fn is_supported() -> bool {
+        let key = HSTRING::from(format!("CLSID\\{{{:?}}}", RealTimeStylus));
+        let mut handle = HKEY::default();
+
+        // the class is registered if its key exists
+        let registered =
+            unsafe { RegOpenKeyExW(HKEY_CLASSES_ROOT, &key, 0, KEY_READ, &mut handle) }
+                == ERROR_SUCCESS;
+
+        if registered {
+            unsafe { RegCloseKey(handle) };
+        }
+
+        let digitizer = unsafe { GetSystemMetrics(SM_DIGITIZER) } as u32;
+
+        registered && digitizer & NID_READY != 0
+    }
+
+    /// Reads options saved by [`save_config`](Self::save_config), to pass to [`init_options`](Self::init_options).
+    #[cfg(feature = "serde")]
+    pub fn load_config(path: impl AsRef<std::path::Path>) -> EasyTabResult<EasyTabOptions> {
+        let config =
+            std::fs::read_to_string(path).map_err(|e| EasyTabError::Config(e.to_string()))?;
+
+        serde_json::from_str(&config).map_err(|e| EasyTabError::Config(e.to_string()))
+    }
+
+    /// Saves the options the tablet was created with to a file as JSON, including the settings changed since (such as
+    /// the [`active_area`](EasyTabOptions::active_area) and [`pressure_range`](EasyTabOptions::pressure_range)), so
+    /// they can be restored with [`load_config`](Self::load_config) in a later session.
+    #[cfg(feature = "serde")]
+    pub fn save_config(&self, path: impl AsRef<std::path::Path>) -> EasyTabResult<()> {
+        let mut opts = self.opts.clone();
+        opts.active_area = self.active_area.get();
+        opts.retry_on_change = self.retry_on_change.get();
+        opts.pressure_range = self.pressure_range.get();
+
+        let config =
+            serde_json::to_string_pretty(&opts).map_err(|e| EasyTabError::Config(e.to_string()))?;
+
+        std::fs::write(path, config).map_err(|e| EasyTabError::Config(e.to_string()))
+    }
+
+    /// Creates a tablet that isn't bound to a window yet, for when the tablet has to be stored before a window exists.
+    ///
+    /// Until [`connect`](Self::connect) is called, no events are raised, the getters return their defaults and
+    /// [`enable`](Self::enable) fails with [`EasyTabError::NotInitialized`].
+    ///
+    /// **Note**: As with [`init_options`](Self::init_options), COM has to be initialised first.
+    pub fn disconnected() -> EasyTabResult<Self> {
+        Self::disconnected_options(EasyTabOptions::default())
+    }
+
+    /// Creates a tablet with the given options that isn't bound to a window yet. Refer to
+    /// [`disconnected`](Self::disconnected).
+    pub fn disconnected_options(opts: EasyTabOptions) -> EasyTabResult<Self> {
+        // create a real time stylus
+        let stylus: IRealTimeStylus = unsafe {
+            CoCreateInstance(&RealTimeStylus, InParam::null(), CLSCTX_INPROC_SERVER)
+                .map_err(ERROR_FN)?
+        };
+
+        // ask for every property we know how to decode
+        unsafe {
+            stylus
+                .SetDesiredPacketDescription(DESIRED_PACKET_PROPERTIES)
+                .map_err(ERROR_FN)?
+        };
+
+        Ok(Self(Rc::new(__InnerTablet {
+            active_area: Cell::new(opts.active_area),
+            pressure_range: Cell::new(opts.pressure_range.filter(|(min, max)| max > min)),
+            retry_on_change: Cell::new(opts.retry_on_change),
+            #[cfg(feature = "dispatch-thread")]
+            dispatcher: opts.dispatch_thread.then(spawn_dispatcher),
+            #[cfg(feature = "dispatch-thread")]
+            threaded_listeners: RefCell::default(),
+            stylus,
+            hwnd: Cell::default(),
+            connected: Cell::default(),
+            plugin: RefCell::default(),
+            drawing_attributes: RefCell::default(),
+            suppressed_gestures: Cell::default(),
+            expiry_timer: Cell::default(),
+            retry_timer: Cell::default(),
+            opts,
+            thread: std::thread::current().id(),
+
+            listeners: RefCell::default(),
+            next_listener: Cell::default(),
+            on_listener: Cell::default(),
+            queue: RefCell::default(),
+            dispatching: Cell::default(),
+            flushed: Cell::default(),
+            deferred: RefCell::default(),
+            pending_packets: RefCell::default(),
+            #[cfg(feature = "history")]
+            history: RefCell::default(),
+            #[cfg(feature = "ipc")]
+            ipc: RefCell::default(),
+
+            active: Cell::default(),
+            x: Cell::default(),
+            y: Cell::default(),
+            pressure: Cell::default(),
+            z: Cell::default(),
+            bounds: Cell::default(),
+            last_sample: Cell::default(),
+            mapping_target: Cell::new(virtual_screen_rect()),
+            client_rect: Cell::default(),
+            in_window: Cell::default(),
+            dpi: Cell::new(USER_DEFAULT_SCREEN_DPI),
+            cursor_id: Cell::default(),
+            tablet_context: Cell::default(),
+            contacts: RefCell::default(),
+            contact_seen: RefCell::default(),
+            pending_up: Cell::default(),
+            cursors: RefCell::default(),
+            primary_contact: Cell::default(),
+            capabilities: Cell::default(),
+            #[cfg(feature = "filters")]
+            reported_pressure: Cell::default(),
+            #[cfg(feature = "filters")]
+            above_threshold: Cell::default(),
+            max_pressure_reached: Cell::default(),
+            #[cfg(feature = "strokes")]
+            current_stroke: RefCell::default(),
+            #[cfg(feature = "strokes")]
+            last_stroke: Cell::default(),
+            #[cfg(feature = "filters")]
+            last_move_at: Cell::default(),
+            relative_origin: Cell::default(),
+            #[cfg(feature = "gestures")]
+            tap_start: Cell::default(),
+            #[cfg(feature = "gestures")]
+            last_tap: Cell::default(),
+            #[cfg(feature = "gestures")]
+            air_press: Cell::default(),
+            #[cfg(feature = "histogram")]
+            pressure_histogram: RefCell::new([0; HISTOGRAM_BINS]),
+
+            #[cfg(feature = "raw-packets")]
+            raw_packet_callback: RefCell::default(),
+            on_connect: RefCell::default(),
+            on_disconnect: RefCell::default(),
+            coordinate_mapper: RefCell::default(),
+            event_transform: RefCell::default(),
+            packet_descriptions: RefCell::default(),
+            device_ids: RefCell::default(),
+        })))
+    }
+
+    /// Binds the tablet to a window.
+    ///
+    /// The window can be a child window, e.g. a dedicated canvas control, in which case only input over the control is
+    /// received and [`to_window`](Self::to_window) maps into the control's client area. Set
+    /// [`EasyTabOptions::map_to_window`] to map the whole tablet onto the control.
+    ///
+    /// A tablet that is already connected is rebound to the new window, which requires it to be disabled.
+    pub fn connect(&self, hwnd: HANDLE_PTR) -> EasyTabResult<()> {
+        // bind the stylus to the window
+        unsafe { self.stylus.SetHWND(hwnd).map_err(ERROR_FN)? };
+        self.hwnd.set(HWND(hwnd.0 as isize));
+
+        // older versions of windows just keep drawing the feedback, which isn't worth failing over
+        if self.opts.suppress_touch_feedback && !suppress_touch_feedback(self.hwnd.get()) {
+            self.handle_event(WinTabEvent::Warning(Warning::TouchFeedbackNotSuppressed))
+                .map_err(ERROR_FN)?;
+        }
+
+        if !self.connected.replace(true) {
+            // pass a reference of ourselves into the handler so it can call the `handle_event` fn
+            let ash: IStylusAsyncPlugin = AsyncStylusHandler(Rc::downgrade(&self.0)).into();
+
+            // add the handler to the stylus
+            if let Err(e) = add_async_plugin(&self.stylus, &ash) {
+                self.connected.set(false);
+
+                return Err(ERROR_FN(e));
+            }
+
+            *self.plugin.borrow_mut() = Some(ash);
+        }
+
+        self.update_mapping().map_err(ERROR_FN)?;
+
+        // there may not be a tablet connected yet, in which case they're read on first use
+        self.capabilities
+            .set(self.read_capabilities().ok().flatten());
+
+        Ok(())
+    }
+
+    /// Binds the tablet to the window behind a [`RawWindowHandle`]. Refer to [`connect`](Self::connect).
+    ///
+    /// Fails with [`EasyTabError::UnsupportedHandle`] if the handle isn't a Win32 one.
+    pub fn rebind_handle(&self, handle: RawWindowHandle) -> EasyTabResult<()> {
+        self.connect(hwnd_from_handle(handle)?)
+    }
+
+    /// Feeds samples through the event pipeline as if they came from the tablet, e.g. to script a drawing demonstration.
+    ///
+    /// Samples with pressure are delivered as [`WinTabEvent::StylusMove`] and the rest as [`WinTabEvent::HoverMove`],
+    /// with [`WinTabEvent::StylusActive`] and [`WinTabEvent::StylusInactive`] raised as the pressure starts and stops.
+    /// The stylus is lifted after the last sample. The listeners and getters see them just like real input.
+    #[cfg(feature = "sim")]
+    pub fn simulate_input(&self, samples: &[StylusSample]) -> EasyTabResult<()> {
+        for sample in samples {
+            let down = sample.pressure > 0.0;
+
+            if down != self.active.get() {
+                self.handle_event(if down {
+                    WinTabEvent::StylusActive
+                } else {
+                    WinTabEvent::StylusInactive
+                })
+                .map_err(ERROR_FN)?;
+            }
+
+            self.handle_event(if down {
+                WinTabEvent::StylusMove(*sample)
+            } else {
+                WinTabEvent::HoverMove(*sample)
+            })
+            .map_err(ERROR_FN)?;
+        }
+
+        if self.active.get() {
+            self.handle_event(WinTabEvent::StylusInactive)
+                .map_err(ERROR_FN)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the DPI of the bound window, `96` being 100% scaling.
+    ///
+    /// The DPI is also re-read whenever the tablet-to-screen mapping changes, and is what
+    /// [`EasyTabOptions::report_dips`] scales by. Calling this from the window's `WM_DPICHANGED` handler picks up a
+    /// window being moved to a monitor with a different scale.
+    ///
+    /// **Note**: Before Windows 10, windows aren't scaled individually, so this is the DPI of the screen.
+    pub fn dpi(&self) -> u32 {
+        let dpi = window_dpi(self.hwnd.get());
+        self.dpi.set(dpi);
+
+        dpi
+    }
+
+    /// Returns the hand the stylus is held in, as set by [`EasyTabOptions::handedness`].
+    pub fn handedness(&self) -> Handedness {
+        self.opts.handedness
+    }
+
+    /// Returns the index of the tablet in use, in the order the tablets are enumerated. Useful for showing which
+    /// tablet was picked when [`EasyTabOptions::index`] is [`WinTabletIndex::Default`].
+    ///
+    /// This is the tablet that last sent packets, or the first tablet if none have yet. Fails with
+    /// [`EasyTabError::NoTablet`] if no tablet is connected.
+    pub fn current_tablet_index(&self) -> EasyTabResult<i32> {
+        let tcid = self
+            .current_tablet_context()
+            .map_err(ERROR_FN)?
+            .ok_or(EasyTabError::NoTablet)?;
+
+        self.tablet_contexts()
+            .map_err(ERROR_FN)?
+            .iter()
+            .position(|&id| id == tcid)
+            .map(|index| index as i32)
+            // the tablet was removed since it last sent packets
+            .ok_or(EasyTabError::NoTablet)
+    }
+
+    /// Starts serving the events over the named pipe `\\.\pipe\<pipe_name>`, so another process (e.g. a
+    /// visualiser or a recorder) can watch the tablet live. Stops any pipe already being served.
+    ///
+    /// Each event is written as a line of JSON. One client can connect at a time, and a new one can connect once it
+    /// disconnects. Events are dropped while no client is connected.
+    ///
+    /// **Note**: The pipe is written from a separate thread, but a client that stops reading eventually stalls it. Up to
+    /// a thousand events queue up in the meantime, after which new events are dropped until it reads again.
+    #[cfg(feature = "ipc")]
+    pub fn start_ipc(&self, pipe_name: &str) -> EasyTabResult<()> {
+        self.stop_ipc();
+
+        let pipe = crate::ipc::EventPipe::create(pipe_name)?;
+        let sender = pipe.sender();
+
+        let id = self.add_listener(Box::new(move |event| {
+            if let Some(line) = crate::ipc::to_line(&event) {
+                // dropped when the backlog is full, e.g. while no client is connected
+                let _ = sender.try_send(line);
+            }
+        }));
+
+        *self.ipc.borrow_mut() = Some((id, pipe));
+
+        Ok(())
+    }
+
+    /// Stops serving the events started with [`start_ipc`](Self::start_ipc), disconnecting any client. Does nothing if
+    /// no pipe is being served.
+    #[cfg(feature = "ipc")]
+    pub fn stop_ipc(&self) {
+        let ipc = self.ipc.borrow_mut().take();
+
+        if let Some((id, pipe)) = ipc {
+            self.remove_listener(id);
+            drop(pipe);
+        }
+    }
+
+    /// Returns an id for the tablet in use that stays the same across reconnects and sessions, for keying per-device
+    /// settings.
+    ///
+    /// This is the tablet's plug and play id, or its name if the driver doesn't report one. Fails with
+    /// [`EasyTabError::NoTablet`] if no tablet is connected, or the tablet reports neither.
+    pub fn device_id(&self) -> EasyTabResult<String> {
+        let tcid = self
+            .current_tablet_context()
+            .map_err(ERROR_FN)?
+            .ok_or(EasyTabError::NoTablet)?;
+
+        if let Some(id) = self.device_ids.borrow().get(&tcid) {
+            return Ok(id.clone());
+        }
+
+        let tablet = unsafe {
+            self.stylus
+                .GetTabletFromTabletContextId(tcid)
+                .map_err(ERROR_FN)?
+        };
+        let info = __InnerTablet::tablet_info(&tablet);
+
+        let id = if !info.plug_and_play_id.is_empty() {
+            info.plug_and_play_id
+        } else if !info.name.is_empty() {
+            info.name
+        } else {
+            return Err(EasyTabError::NoTablet);
+        };
+
+        self.device_ids.borrow_mut().insert(tcid, id.clone());
+
+        Ok(id)
+    }
+
+    /// Returns the handle of the window the tablet is bound to, or `0` if it isn't [connected](Self::connect).
+    ///
+    /// Useful for telling which window a tablet belongs to when managing several.
+    pub fn bound_hwnd(&self) -> usize {
+        if self.connected.get() {
+            self.hwnd.get().0 as usize
+        } else {
+            0
+        }
+    }
+
+    /// Enables the tablet.
+    ///
+    /// Fails with [`EasyTabError::NotInitialized`] if the tablet hasn't been [connected](Self::connect) to a window.
+    pub fn enable(&self) -> EasyTabResult<()> {
+        self.set_enabled(true)
+    }
+
+    /// Disables the tablet.
+    pub fn disable(&self) -> EasyTabResult<()> {
+        self.set_enabled(false)
+    }
+
+    /// Enables or disables the tablet.
+    ///
+    /// Fails with [`EasyTabError::NotInitialized`] if enabling a tablet that hasn't been [connected](Self::connect) to a
+    /// window.
+    pub fn set_enabled(&self, on: bool) -> EasyTabResult<()> {
+        if on && !self.connected.get() {
+            return Err(EasyTabError::NotInitialized);
+        }
+
+        unsafe { self.stylus.SetEnabled(on).map_err(ERROR_FN)? };
+
+        Ok(())
+    }
+
+    /// Returns whether the tablet is enabled. Refer to [`try_is_enabled`](Self::try_is_enabled) to tell a disabled
+    /// tablet from the stylus failing to report it.
+    pub fn is_enabled(&self) -> bool {
+        unsafe { self.stylus.Enabled() }.is_ok_and(|enabled| enabled.as_bool())
+    }
+
+    /// Returns whether the tablet is enabled like [`is_enabled`](Self::is_enabled), but fails if the stylus can't be
+    /// queried, rather than returning `false`.
+    pub fn try_is_enabled(&self) -> EasyTabResult<bool> {
+        let enabled = unsafe { self.stylus.Enabled().map_err(ERROR_FN)? };
+
+        Ok(enabled.as_bool())
+    }
+
+    /// Enables the tablet until the returned guard is dropped.
+    ///
+    /// ```
+    /// {
+    ///     let _guard = tablet.enable_guard()?;
+    ///     // the tablet is enabled while the drawing tool is active
+    /// }
+    /// // and disabled again here
+    /// ```
+    pub fn enable_guard(&self) -> EasyTabResult<EnableGuard<'_>> {
+        self.set_enabled(true)?;
+
+        Ok(EnableGuard { tablet: self })
+    }
+
+    /// Sets the region of the tablet, in tablet coordinates, that is mapped onto the screen. Refer to
+    /// [`EasyTabOptions::active_area`].
+    pub fn set_active_area(&self, area: Option<Rect>) {
+        self.active_area.set(area);
+    }
+
+    /// Sets the region of the tablet that is mapped onto the screen, as `[x0, y0, x1, y1]` fractions of the tablet
+    /// surface (e.g. `[0.0, 0.0, 0.5, 0.5]` for the top left quarter). Easier to drive from a settings UI than
+    /// [`set_active_area`](Self::set_active_area).
+    ///
+    /// The fractions are clamped to `0.0..=1.0` and converted using the tablet [`bounds`](Self::bounds), so this fails
+    /// with [`EasyTabError::NoTablet`] if they aren't known yet.
+    pub fn set_active_area_normalized(&self, rect: [f32; 4]) -> EasyTabResult<()> {
+        let bounds = match self.bounds.get() {
+            Some(bounds) => bounds,
+            None => {
+                let tcid = self
+                    .current_tablet_context()
+                    .map_err(ERROR_FN)?
+                    .ok_or(EasyTabError::NoTablet)?;
+
+                self.packet_description(tcid)
+                    .map_err(ERROR_FN)?
+                    .bounds()
+                    .ok_or(EasyTabError::NoTablet)?
+            }
+        };
+
+        self.active_area.set(Some(bounds.denormalize_rect(rect)));
+
+        Ok(())
+    }
+
+    /// Sets whether newly connected tablets are re-initialised. Refer to [`EasyTabOptions::retry_on_change`].
+    ///
+    /// Useful for only reconnecting during an active drawing session, avoiding the churn of retries otherwise. Turning
+    /// it off cancels any retry still waiting.
+    pub fn set_retry_on_change(&self, on: bool) {
+        self.retry_on_change.set(on);
+
+        if !on {
+            self.stop_retry_timer();
+        }
+    }
+
+    /// Sets the callback that is called for every tablet event, replacing the one set by the previous call.
+    ///
+    /// Listeners registered with [`add_listener`](Self::add_listener) are unaffected.
+    pub fn on(&self, cb: Box<dyn Fn(WinTabEvent)>) {
+        if let Some(id) = self.on_listener.take() {
+            self.remove_listener(id);
+        }
+
+        self.on_listener.set(Some(self.add_listener(cb)));
+    }
+
+    /// Registers a callback that is called for every tablet event.
+    ///
+    /// Listeners are called in the order they were added. The returned id can be passed to [`remove_listener`](Self::remove_listener).
+    ///
+    /// Listeners can add and remove listeners (including themselves) while they are called. A listener added this way
+    /// is first called for the next event.
+    pub fn add_listener(&self, cb: Box<dyn Fn(WinTabEvent)>) -> ListenerId {
+        self.add_responding_listener(Box::new(move |event| {
+            cb(event);
+            EventResponse::Continue
+        }))
+    }
+
+    /// Registers a callback that is called for every tablet event, and can mark the event as handled.
+    ///
+    /// If any listener returns [`EventResponse::Handled`], the event is consumed: for packet events this means the
+    /// packets are not passed on to the other async plugins on the stylus (e.g. ones added by a UI framework sharing
+    /// it), as the tablet's plugin is inserted ahead of them. For [system gestures](WinTabEvent::SystemGesture), it turns
+    /// off the action Windows ties to the gesture where it can.
+    ///
+    /// Listeners are called in the order they were added. The returned id can be passed to [`remove_listener`](Self::remove_listener).
+    pub fn add_responding_listener(
+        &self,
+        cb: Box<dyn Fn(WinTabEvent) -> EventResponse>,
+    ) -> ListenerId {
+        self.add_filtered_listener(EventMask::all(), 0, cb)
+    }
+
+    /// Registers a callback that is only called for the kinds of events in `mask`, and can mark the event as handled.
+    ///
+    /// Listeners are called from the highest `priority` to the lowest, and in the order they were added within a
+    /// priority. The other listeners have a priority of `0`. A listener returning [`EventResponse::Stop`] stops the
+    /// event reaching the listeners after it, so a high priority listener can capture the input:
+    ///
+    /// ```
+    /// tablet.add_filtered_listener(EventMask::MOVE, 100, Box::new(move |event| {
+    ///     if overlay.is_open() {
+    ///         overlay.handle(event);
+    ///         EventResponse::Stop
+    ///     } else {
+    ///         EventResponse::Continue
+    ///     }
+    /// }));
+    /// ```
+    ///
+    /// The returned id can be passed to [`remove_listener`](Self::remove_listener).
+    pub fn add_filtered_listener(
+        &self,
+        mask: EventMask,
+        priority: i32,
+        cb: Box<dyn Fn(WinTabEvent) -> EventResponse>,
+    ) -> ListenerId {
+        let id = ListenerId(self.next_listener.get());
+        self.next_listener.set(id.0 + 1);
+
+        let mut listeners = self.listeners.borrow_mut();
+        let index = listener_index(&listeners, priority);
+
+        listeners.insert(
+            index,
+            Listener {
+                id,
+                mask,
+                priority,
+                cb: cb.into(),
+            },
+        );
+
+        id
+    }
+
+    /// Registers a callback that is called for every tablet event, on the dispatcher thread when
+    /// [`EasyTabOptions::dispatch_thread`] is set.
+    ///
+    /// Without the dispatcher thread, this is the same as [`add_listener`](Self::add_listener).
+    ///
+    /// **Note**: The dispatcher thread receives events after the listeners on this thread have been called, so it can't
+    /// mark them as handled.
+    #[cfg(feature = "dispatch-thread")]
+    pub fn add_threaded_listener(&self, cb: Box<dyn Fn(WinTabEvent) + Send>) -> ListenerId {
+        let dispatcher = match &self.dispatcher {
+            Some(dispatcher) => dispatcher,
+            None => return self.add_listener(cb),
+        };
+
+        let id = ListenerId(self.next_listener.get());
+        self.next_listener.set(id.0 + 1);
+
+        // the dispatcher only stops once the tablet is dropped, so sending can't fail here
+        let _ = dispatcher.send(DispatcherMessage::AddListener(id, cb));
+        self.threaded_listeners.borrow_mut().insert(id);
+
+        id
+    }
+
+    /// Removes a listener previously registered with [`add_listener`](Self::add_listener),
+    /// [`add_responding_listener`](Self::add_responding_listener) or
+    /// [`add_threaded_listener`](Self::add_threaded_listener).
+    ///
+    /// Returns `false` if no listener with the given id exists, e.g. because it was already removed. Listeners on the
+    /// dispatcher thread are removed asynchronously, so one may still be called for events already sent to it.
+    pub fn remove_listener(&self, id: ListenerId) -> bool {
+        #[cfg(feature = "dispatch-thread")]
+        if let Some(dispatcher) = &self.dispatcher {
+            if self.threaded_listeners.borrow_mut().remove(&id) {
+                let _ = dispatcher.send(DispatcherMessage::RemoveListener(id));
+
+                return true;
+            }
+        }
+
+        let mut listeners = self.listeners.borrow_mut();
+        let len = listeners.len();
+
+        listeners.retain(|listener| listener.id != id);
+
+        listeners.len() != len
+    }
+
+    /// Registers a listener and returns the tablet, so listeners can be chained onto [`init`](Self::init).
+    ///
+    /// ```
+    /// let tablet = EasyTablet::init(hwnd)?
+    ///     .with_listener(Box::new(|event| println!("{:?}", event)))
+    ///     .with_listener(Box::new(|event| log(event)));
+    /// ```
+    #[must_use = "the tablet is dropped, disabling it and removing its listeners, if it isn't used"]
+    pub fn with_listener(self, cb: Box<dyn Fn(WinTabEvent)>) -> Self {
+        self.add_listener(cb);
+        self
+    }
+
+    /// Sets a callback that receives every packet before it is decoded, or clears it with `None`.
+    ///
+    /// The callback is given the raw packet values and a map from each packet property's GUID (e.g.
+    /// `GUID_PACKETPROPERTY_GUID_X`) to its index within the packet, so only the needed values have to be read.
+    ///
+    /// **Note**: The packet slice is only valid for the duration of the call, and the callback must not call
+    /// `raw_packet_callback` itself.
+    #[cfg(feature = "raw-packets")]
+    pub fn raw_packet_callback(&self, cb: Option<RawPacketCallback>) {
+        *self.raw_packet_callback.borrow_mut() = cb;
+    }
+
+    /// Returns a stream of every event from now on.
+    ///
+    /// The stream works with any executor, but events are still raised by the message loop of the thread the tablet was
+    /// created on, so that thread must keep pumping messages for the stream to make progress.
+    ///
+    /// **Note**: The stream is unbounded. Dropping it stops events being buffered, but the forwarding listener stays
+    /// registered for the tablet's lifetime.
+    #[cfg(feature = "futures")]
+    pub fn event_stream(&self) -> impl futures::Stream<Item = WinTabEvent> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+
+        self.add_listener(Box::new(move |event| {
+            // the receiver may have been dropped, in which case there's no one left to tell
+            let _ = tx.unbounded_send(event);
+        }));
+
+        rx
+    }
+
+    /// Sets a function that maps every sample's position before it is emitted, replacing the built-in tablet-to-screen
+    /// mapping, or reverts to the built-in mapping with `None`.
+    ///
+    /// The function is given the position in tablet coordinates and returns the position the events, and getters such
+    /// as [`x`](Self::x), should report. Useful for setups the built-in options don't cover.
+    ///
+    /// **Note**: The function must not call `set_coordinate_mapper` itself.
+    pub fn set_coordinate_mapper(&self, f: Option<CoordinateMapper>) {
+        *self.coordinate_mapper.borrow_mut() = f;
+    }
+
+    /// Sets a function that every event passes through before it's handled, or removes it with `None`.
+    ///
+    /// The function can return the event as is, a different event in its place (e.g. with the coordinates clamped), or
+    /// `None` to drop it. The tablet state, and so getters such as [`x`](Self::x), follow the events it returns.
+    ///
+    /// ```
+    /// // ignore the stylus while it hovers
+    /// tablet.set_event_transform(Some(Box::new(|event| match event {
+    ///     WinTabEvent::HoverMove(_) => None,
+    ///     event => Some(event),
+    /// })));
+    /// ```
+    ///
+    /// **Note**: The function must not call `set_event_transform` itself.
+    pub fn set_event_transform(&self, f: Option<EventTransform>) {
+        *self.event_transform.borrow_mut() = f;
+    }
+
+    /// Overrides the raw pressure range that pressure is normalised against, for devices that misreport it (or for
+    /// testing), or reverts to the range the device reports with `None`.
+    ///
+    /// The range is `(min, max)` in raw pressure values. Pressure outside it is clamped to `0.0..=1.0`. An override
+    /// also takes the place of any pressure [quirk](Self::applied_quirks).
+    pub fn set_pressure_range(&self, range: Option<(i32, i32)>) {
+        self.pressure_range
+            .set(range.filter(|(min, max)| max > min));
+    }
+
+    /// Sets whether ink drawn from the tablet's input is fitted to a curve, the smoothing the Windows ink renderers
+    /// apply. It is set on the [`drawing_attributes`](Self::drawing_attributes), so only affects renderers given those.
+    ///
+    /// **Note**: This doesn't change the input. The samples, events and getters always report the points as the
+    /// digitiser sent them, as the crate doesn't smooth them itself.
+    pub fn set_driver_smoothing(&self, on: bool) -> EasyTabResult<()> {
+        apply_driver_smoothing(&self.drawing_attributes()?, on).map_err(ERROR_FN)
+    }
+
+    /// Returns whether ink is fitted to a curve. Refer to [`set_driver_smoothing`](Self::set_driver_smoothing).
+    pub fn driver_smoothing(&self) -> EasyTabResult<bool> {
+        unsafe { self.drawing_attributes()?.FitToCurve() }
+            .map(|flag| flag != VARIANT_FALSE)
+            .map_err(ERROR_FN)
+    }
+
+    /// Returns the ink drawing attributes for the tablet's input, to pass to an ink renderer (such as an
+    /// `IDynamicRenderer`) so it follows [`set_driver_smoothing`](Self::set_driver_smoothing).
+    pub fn drawing_attributes(&self) -> EasyTabResult<IInkDrawingAttributes> {
+        if let Some(attributes) = self.drawing_attributes.borrow().as_ref() {
+            return Ok(attributes.clone());
+        }
+
+        let attributes: IInkDrawingAttributes = unsafe {
+            CoCreateInstance(&InkDrawingAttributes, InParam::null(), CLSCTX_INPROC_SERVER)
+                .map_err(ERROR_FN)?
+        };
+
+        *self.drawing_attributes.borrow_mut() = Some(attributes.clone());
+
+        Ok(attributes)
+    }
+
+    /// Sets a function called when a tablet is connected to the system, with a description of the tablet, or removes
+    /// it with `None`.
+    ///
+    /// Unlike the events, this is called whether or not [`retry_on_change`](EasyTabOptions::retry_on_change) is set,
+    /// so it can be used to update UI state (e.g. re-enabling tablet tools).
+    ///
+    /// **Note**: The function must not call `on_connect` itself.
+    pub fn on_connect(&self, f: Option<ConnectCallback>) {
+        *self.on_connect.borrow_mut() = f;
+    }
+
+    /// Sets a function called when a tablet is disconnected from the system, or removes it with `None`. Refer to
+    /// [`on_connect`](Self::on_connect).
+    ///
+    /// **Note**: The function must not call `on_disconnect` itself.
+    pub fn on_disconnect(&self, f: Option<Box<dyn Fn()>>) {
+        *self.on_disconnect.borrow_mut() = f;
+    }
+
+    /// Waits for the next event, pumping this thread's messages while waiting.
+    ///
+    /// Returns the oldest queued event straight away if there is one, or `None` if no event arrives before the timeout.
+    /// Listeners still receive every event as normal.
+    ///
+    /// **Note**: Only the most recent events are queued, so events may be missed if this isn't called often enough.
+    pub fn next_event(&self, timeout: Duration) -> EasyTabResult<Option<WinTabEvent>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            self.decode_pending().map_err(ERROR_FN)?;
+
+            if let Some(event) = self.queue.borrow_mut().pop_front() {
+                return Ok(Some(event));
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            wait_for_messages(deadline - now);
+            pump_messages();
+        }
+    }
+
+    /// Waits for the next stroke, pumping this thread's messages from the stylus touching the digitiser until it is
+    /// lifted again. Returns `None` if the stroke isn't finished before the timeout.
+    ///
+    /// A stroke already in progress when this is called is ignored, as are strokes without any samples. Listeners still
+    /// receive every event as normal.
+    ///
+    /// Fails with [`EasyTabError::InListener`] when called from a listener, as no events are delivered until the
+    /// listener returns.
+    #[cfg(feature = "strokes")]
+    pub fn wait_for_stroke(&self, timeout: Duration) -> EasyTabResult<Option<Stroke>> {
+        // events raised from inside a listener are only delivered once it returns, so this would never see the stroke
+        if self.dispatching.get() {
+            return Err(EasyTabError::InListener);
+        }
+
+        let deadline = Instant::now() + timeout;
+
+        let finished: Rc<RefCell<Option<Stroke>>> = Rc::default();
+
+        let recorder = {
+            let finished = finished.clone();
+
+            StrokeRecorder::new(
+                self,
+                Box::new(move |stroke| {
+                    finished.borrow_mut().get_or_insert(stroke);
+                }),
+            )
+        };
+
+        let result = loop {
+            if let Err(e) = self.decode_pending() {
+                break Err(ERROR_FN(e));
+            }
+
+            if let Some(stroke) = finished.borrow_mut().take() {
+                break Ok(Some(stroke));
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                break Ok(None);
+            }
+
+            wait_for_messages(deadline - now);
+            pump_messages();
+        };
+
+        recorder.detach(self);
+
+        result
+    }
+
+    /// Returns every queued event, oldest first, without waiting.
+    ///
+    /// With [`EasyTabOptions::lazy_decode`], this is where the packets received since the last call are decoded (and
+    /// the listeners called for them).
+    pub fn drain_events(&self) -> EasyTabResult<Vec<WinTabEvent>> {
+        self.decode_pending().map_err(ERROR_FN)?;
+
+        Ok(self.queue.borrow_mut().drain(..).collect())
+    }
+
+    /// Processes every packet the stylus has already received, pumping this thread's messages until they have all been
+    /// delivered to the listeners.
+    ///
+    /// Useful at sync points (e.g. before taking a snapshot) to make sure [`last_sample`](Self::last_sample) and the other
+    /// getters are up to date. Fails with [`EasyTabError::Timeout`] after a second if the stylus doesn't catch up, e.g.
+    /// because it is disabled.
+    pub fn flush(&self) -> EasyTabResult<()> {
+        self.flushed.set(false);
+
+        // the marker is delivered in order with the packets, so once it arrives everything before it has been too
+        unsafe {
+            self.stylus
+                .AddCustomStylusDataToQueue(AsyncStylusQueue, &FLUSH_MARKER, &[0])
+                .map_err(ERROR_FN)?
+        };
+
+        let deadline = Instant::now() + FLUSH_TIMEOUT;
+
+        while !self.flushed.get() {
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Err(EasyTabError::Timeout);
+            }
+
+            wait_for_messages(deadline - now);
+            pump_messages();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of asynchronous plugins added to the stylus, including the one this crate adds.
+    ///
+    /// Useful for checking the plugin pipeline when other code adds its own plugins to the same stylus.
+    pub fn stylus_async_plugin_count(&self) -> EasyTabResult<u32> {
+        unsafe { self.stylus.GetStylusAsyncPluginCount().map_err(ERROR_FN) }
+    }
+
+    /// Returns the number of synchronous plugins added to the stylus. This crate doesn't add any itself.
+    pub fn stylus_sync_plugin_count(&self) -> EasyTabResult<u32> {
+        unsafe { self.stylus.GetStylusSyncPluginCount().map_err(ERROR_FN) }
+    }
+
+    /// Returns whether the window the tablet is bound to is in the foreground.
+    ///
+    /// For a child window, this checks the top-level window that contains it.
+    pub fn window_has_focus(&self) -> bool {
+        unsafe { GetForegroundWindow() == GetAncestor(self.hwnd.get(), GA_ROOT) }
+    }
+
+    /// Returns the GUIDs of the buttons on the cursor that last interacted with the tablet.
+    ///
+    /// The buttons are returned in the order the device reports them, so the index of a GUID can be used as the button
+    /// number (e.g. for "Button 1 / Button 2" settings).
+    pub fn button_guids(&self) -> EasyTabResult<Vec<GUID>> {
+        self.0.cursor_buttons(self.cursor_id.get())
+    }
+
+    /// Returns whether a finger or stylus is activating the digitiser.
+    pub fn active(&self) -> bool {
+        self.active.get()
+    }
+
+    /// Returns the x position where the finger or stylus is making contact with the digitiser.
+    pub fn x(&self) -> i32 {
+        self.x.get()
+    }
+
+    /// Returns the y position where the finger or stylus is making contact with the digitiser.
+    pub fn y(&self) -> i32 {
+        self.y.get()
+    }
+
+    /// Returns the pressure of the finger or stylus on the digitiser.
+    pub fn pressure(&self) -> f32 {
+        self.pressure.get()
+    }
+
+    /// Returns the x position like [`x`](Self::x), but fails with [`EasyTabError::NotInitialized`] if the tablet isn't
+    /// [connected](Self::connect) to a window, rather than returning `0`.
+    pub fn try_x(&self) -> EasyTabResult<i32> {
+        self.connected_or_err()?;
+
+        Ok(self.x.get())
+    }
+
+    /// Returns the y position like [`y`](Self::y), but fails with [`EasyTabError::NotInitialized`] if the tablet isn't
+    /// [connected](Self::connect) to a window, rather than returning `0`.
+    pub fn try_y(&self) -> EasyTabResult<i32> {
+        self.connected_or_err()?;
+
+        Ok(self.y.get())
+    }
+
+    /// Returns the pressure like [`pressure`](Self::pressure), but fails with [`EasyTabError::NotInitialized`] if the
+    /// tablet isn't [connected](Self::connect) to a window, so no pressure can be told apart from no tablet.
+    pub fn try_pressure(&self) -> EasyTabResult<f32> {
+        self.connected_or_err()?;
+
+        Ok(self.pressure.get())
+    }
+
+    fn connected_or_err(&self) -> EasyTabResult<()> {
+        if self.connected.get() {
+            Ok(())
+        } else {
+            Err(EasyTabError::NotInitialized)
+        }
+    }
+
+    /// Returns the pressure of the stylus if it is over the given rect, or `None` if it's elsewhere. The rect is in the
+    /// same coordinates as [`x`](Self::x) and [`y`](Self::y).
+    ///
+    /// Useful for pressure-sensitive controls, e.g. a slider driven by how hard it's pressed. The pressure is `0.0`
+    /// while the stylus hovers over the rect.
+    pub fn pressure_at_point(&self, rect: Rect) -> Option<f32> {
+        rect.contains(self.x.get(), self.y.get())
+            .then(|| self.pressure.get())
+    }
+
+    /// Returns the height of the stylus above the digitiser, or `None` if the tablet doesn't report it.
+    pub fn z(&self) -> Option<i32> {
+        self.z.get()
+    }
+
+    /// Returns what the connected tablet supports.
+    ///
+    /// The capabilities are read once and cached until a tablet is connected or disconnected. Returns the default (no
+    /// capabilities) if no tablet is connected.
+    pub fn capabilities(&self) -> EasyTabResult<Capabilities> {
+        if let Some(capabilities) = self.capabilities.get() {
+            return Ok(capabilities);
+        }
+
+        let capabilities = self.read_capabilities().map_err(ERROR_FN)?;
+        self.capabilities.set(capabilities);
+
+        Ok(capabilities.unwrap_or_default())
+    }
+
+    /// Returns the kinds of events the connected tablet can produce with the options the tablet was created with, e.g. to
+    /// hide the pressure settings for a tablet without pressure.
+    ///
+    /// Derived from the [`capabilities`](Self::capabilities), so with no tablet connected only the events every tablet
+    /// produces are included. [`EventMask::GESTURES`] is always included, as Windows reports
+    /// [system gestures](WinTabEvent::SystemGesture) for every tablet.
+    pub fn supported_events(&self) -> EasyTabResult<EventMask> {
+        Ok(supported_events(self.capabilities()?, &self.opts))
+    }
+
+    /// Returns the greatest height above the digitiser at which the stylus is still detected, in the same units as
+    /// [`z`](Self::z), or `None` if the tablet doesn't report the height.
+    pub fn max_hover_distance(&self) -> EasyTabResult<Option<i32>> {
+        let tcid = match self.current_tablet_context().map_err(ERROR_FN)? {
+            Some(tcid) => tcid,
+            None => return Ok(None),
+        };
+
+        let description = self.packet_description(tcid).map_err(ERROR_FN)?;
+
+        Ok(description
+            .metrics(&GUID_PACKETPROPERTY_GUID_Z)
+            .map(|m| m.nLogicalMax))
+    }
+
+    /// Returns the number of pressure levels the tablet reports (e.g. `8192`), or `None` if it doesn't report pressure.
+    pub fn pressure_levels(&self) -> EasyTabResult<Option<u32>> {
+        let tcid = match self.current_tablet_context().map_err(ERROR_FN)? {
+            Some(tcid) => tcid,
+            None => return Ok(None),
+        };
+
+        let description = self.packet_description(tcid).map_err(ERROR_FN)?;
+
+        Ok(description
+            .metrics(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE)
+            .filter(|m| m.nLogicalMax >= m.nLogicalMin)
+            .map(|m| (m.nLogicalMax as i64 - m.nLogicalMin as i64 + 1) as u32))
+    }
+
+    /// Returns the most recent packet from the tablet, or `None` if no packets have been received yet.
+    pub fn last_sample(&self) -> Option<StylusSample> {
+        self.last_sample.get()
+    }
+
+    /// Returns every contact currently touching the digitiser, in no particular order.
+    ///
+    /// This is the polling counterpart to the stylus events, and is mostly useful for multi-touch digitisers where more
+    /// than one contact can be down at once. Returns an empty `Vec` when nothing is touching.
+    pub fn active_contacts(&self) -> Vec<Contact> {
+        self.contacts.borrow().values().copied().collect()
+    }
+
+    /// Returns whether the tablet is built into a display or is separate from it, which affects how input is best mapped
+    /// (e.g. a pen display maps naturally onto its own monitor).
+    ///
+    /// When Windows reports only one kind of pen digitiser, that is used. Otherwise, the tablet is assumed to be
+    /// integrated if its [`bounds`](Self::bounds) have the same aspect ratio as one of the monitors, and external if
+    /// not. Returns [`TabletKind::Unknown`] if neither works, e.g. because no packets have been received yet.
+    pub fn tablet_kind(&self) -> TabletKind {
+        let digitizer = unsafe { GetSystemMetrics(SM_DIGITIZER) } as u32;
+
+        match (
+            digitizer & NID_INTEGRATED_PEN != 0,
+            digitizer & NID_EXTERNAL_PEN != 0,
+        ) {
+            (true, false) => return TabletKind::Integrated,
+            (false, true) => return TabletKind::External,
+            _ => {}
+        }
+
+        match self.bounds.get() {
+            Some(bounds) => kind_from_bounds(&bounds, &monitor_rects()),
+            None => TabletKind::Unknown,
+        }
+    }
+
+    /// Returns the kind of tip on the cursor that last interacted with the tablet, so apps can pick a matching brush.
+    ///
+    /// Returns [`TipType::Unknown`] when the driver doesn't say, or no cursor has interacted with the tablet yet.
+    pub fn tip_type(&self) -> TipType {
+        let cursor = match unsafe { self.stylus.GetStylusForId(self.cursor_id.get()) } {
+            Ok(cursor) => cursor,
+            Err(_) => return TipType::Unknown,
+        };
+
+        let name = unsafe { cursor.Name() }.map(|name| name.to_string());
+        let inverted = unsafe { cursor.Inverted() }.is_ok_and(|inverted| inverted != 0);
+
+        TipType::from_cursor(&name.unwrap_or_default(), inverted)
+    }
+
+    /// Returns the statistics of the last stroke to finish, or `None` if no stroke has finished yet.
+    ///
+    /// A stroke runs from the stylus touching the digitiser to it lifting, and only the primary contact's samples are
+    /// counted.
+    #[cfg(feature = "strokes")]
+    pub fn last_stroke_stats(&self) -> Option<StrokeStats> {
+        self.last_stroke.get()
+    }
+
+    /// Returns the state of a cursor, or `None` if it hasn't interacted with the tablet.
+    ///
+    /// Some tablets track several cursors at once (e.g. a pen and a puck), each with its own id. Their state is kept
+    /// separately, so one cursor's samples don't overwrite another's. The id of the cursor a sample is from is in
+    /// [`StylusSample::cursor`].
+    pub fn state_for_cursor(&self, id: u32) -> Option<CursorState> {
+        self.cursors.borrow().get(&id).copied()
+    }
+
+    /// Returns the id of the primary contact, or `None` if there isn't one.
+    ///
+    /// The primary contact is the first to touch the digitiser while nothing else was. Once it lifts, there is no
+    /// primary contact until every other contact has lifted too. While it is down, [`x`](Self::x), [`y`](Self::y) and
+    /// [`pressure`](Self::pressure) follow it rather than whichever contact moved last.
+    pub fn primary_contact_id(&self) -> Option<u32> {
+        self.primary_contact.get()
+    }
+
+    /// Returns the tilt of the stylus along the x and y axes in degrees, or `None` if the tablet doesn't report it.
+    pub fn tilt(&self) -> Option<(f32, f32)> {
+        self.last_sample.get()?.tilt
+    }
+
+    /// Returns the pitch of the stylus in degrees, or `None` if the pen doesn't report it. Refer to
+    /// [`StylusSample::pitch`].
+    pub fn pitch(&self) -> Option<f32> {
+        self.last_sample.get()?.pitch
+    }
+
+    /// Returns the roll of the stylus in degrees, or `None` if the pen doesn't report it. Refer to
+    /// [`StylusSample::roll`].
+    pub fn roll(&self) -> Option<f32> {
+        self.last_sample.get()?.roll
+    }
+
+    /// Returns the yaw of the stylus in degrees, or `None` if the pen doesn't report it. Refer to
+    /// [`StylusSample::yaw`].
+    pub fn yaw(&self) -> Option<f32> {
+        self.last_sample.get()?.yaw
+    }
+
+    /// Returns the direction the stylus leans in, in degrees, or `None` if the tablet doesn't report tilt.
+    ///
+    /// Refer to [`tilt_to_spherical`](crate::tilt_to_spherical) for more info.
+    pub fn azimuth(&self) -> Option<f32> {
+        self.last_sample.get()?.azimuth
+    }
+
+    /// Returns the angle between the stylus and the surface, in degrees, or `None` if the tablet doesn't report tilt.
+    ///
+    /// Refer to [`tilt_to_spherical`](crate::tilt_to_spherical) for more info.
+    pub fn altitude(&self) -> Option<f32> {
+        self.last_sample.get()?.altitude
+    }
+
+    /// Returns the serial number of the pen, or `None` if the tablet doesn't report it.
+    ///
+    /// Useful for keeping per-pen settings, as it distinguishes individual physical pens.
+    pub fn pen_serial(&self) -> Option<i32> {
+        self.last_sample.get()?.serial
+    }
+
+    /// Returns the width and height of the most recent contact, or `None` if the digitiser doesn't report them.
+    ///
+    /// Touch digitisers report the size of the area in contact, which can tell a fingertip from a palm.
+    pub fn contact_size(&self) -> Option<(i32, i32)> {
+        self.last_sample.get()?.contact_size
+    }
+
+    /// Returns how many samples have been seen at each pressure since the tablet was created, or the histogram was last
+    /// [reset](Self::reset_histogram).
+    ///
+    /// The normalised pressure range is split into `buckets` equal buckets, lowest first. Only samples from a contact
+    /// that is down with non-zero pressure are counted. Useful for visualising a user's natural pressure when
+    /// calibrating a pressure curve.
+    #[cfg(feature = "histogram")]
+    pub fn pressure_histogram(&self, buckets: usize) -> Vec<u32> {
+        merge_histogram(&self.pressure_histogram.borrow(), buckets)
+    }
+
+    /// Clears the samples counted by [`pressure_histogram`](Self::pressure_histogram).
+    #[cfg(feature = "histogram")]
+    pub fn reset_histogram(&self) {
+        *self.pressure_histogram.borrow_mut() = [0; HISTOGRAM_BINS];
+    }
+
+    /// Re-reads the packet layout of the connected tablet, and the [bounds](Self::bounds) and
+    /// [capabilities](Self::capabilities) derived from it.
+    ///
+    /// The layout is cached, so if a driver changes it at runtime the packets would be decoded wrong until this is
+    /// called. It is already called whenever the tablet-to-screen mapping changes. Packets waiting to be decoded (with
+    /// [`EasyTabOptions::lazy_decode`]) are decoded with the old layout first.
+    pub fn refresh_packet_description(&self) -> EasyTabResult<()> {
+        self.decode_pending().map_err(ERROR_FN)?;
+
+        self.reread_description().map_err(ERROR_FN)
+    }
+
+    /// Returns the workarounds applied to the packets of the tablet that last sent them, for devices known to misreport
+    /// their data. Empty if no packets have been received yet, or [`EasyTabOptions::disable_quirks`] is set.
+    #[cfg(feature = "quirks")]
+    pub fn applied_quirks(&self) -> Vec<Quirk> {
+        let tcid = match self.tablet_context.get() {
+            Some(tcid) => tcid,
+            None => return Vec::new(),
+        };
+
+        self.packet_descriptions
+            .borrow()
+            .get(&tcid)
+            .map(|description| description.quirks.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the status bits of the most recent packet, or `None` if the tablet doesn't report them.
+    ///
+    /// This is the most reliable way to tell whether the tip is down, the eraser is in use or the barrel button is
+    /// pressed, as it comes with each packet rather than from separate notifications.
+    pub fn packet_status(&self) -> Option<PacketStatus> {
+        self.last_sample.get()?.status
+    }
+
+    /// Maps a point from a sample to screen coordinates.
+    ///
+    /// The tablet (or [`EasyTabOptions::active_area`] when set) maps onto the whole desktop, or onto
+    /// [`EasyTabOptions::target_monitor`] or the bound window with [`EasyTabOptions::map_to_window`] when set. Returns
+    /// `None` if the tablet [`bounds`](Self::bounds) aren't known yet.
+    ///
+    /// The point is taken to be in [`EasyTabOptions::emit_coordinate_space`], so points already in screen coordinates
+    /// are returned as is. With a [coordinate mapper](Self::set_coordinate_mapper) set, the points are already mapped
+    /// and are returned as is too.
+    pub fn to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        match self.opts.emit_coordinate_space {
+            CoordinateSpace::Tablet => self.0.screen_point(x, y),
+            CoordinateSpace::Screen => Some((x, y)),
+            CoordinateSpace::Window => Some(self.0.client_to_screen((x, y))),
+        }
+    }
+
+    /// Maps a point from a sample to the client coordinates of the bound window.
+    ///
+    /// The point is taken to be in [`EasyTabOptions::emit_coordinate_space`], so points already in client coordinates
+    /// are returned as is. Refer to [`to_screen`](Self::to_screen) for more info.
+    pub fn to_window(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        match self.opts.emit_coordinate_space {
+            CoordinateSpace::Tablet => self.0.window_point(x, y),
+            CoordinateSpace::Screen => Some(self.0.screen_to_client((x, y))),
+            CoordinateSpace::Window => Some((x, y)),
+        }
+    }
+
+    /// Returns the client area of the bound window, in screen coordinates.
+    ///
+    /// This is the rect [`to_window`](Self::to_window) maps screen coordinates into.
+    pub fn window_client_rect(&self) -> EasyTabResult<Rect> {
+        self.0.client_rect()
+    }
+
+    /// Returns the range of coordinates the tablet reports, or `None` if no packets have been received yet.
+    pub fn bounds(&self) -> Option<TabletBounds> {
+        self.bounds.get()
+    }
+
+    /// Returns the current position normalised to `[0, 1]` on both axes, using the tablet [`bounds`](Self::bounds)
+    /// (or [`EasyTabOptions::active_area`] when set).
+    ///
+    /// The position is normalised over the region the tablet maps onto, whichever
+    /// [coordinate space](EasyTabOptions::emit_coordinate_space) the samples are in.
+    pub fn normalized_position(&self) -> Option<(f32, f32)> {
+        self.emit_bounds().map(|b| b.normalize(self.x(), self.y()))
+    }
+
+    /// Returns the current position normalised to `[-1, 1]` on both axes, with `(0, 0)` at the centre of the tablet.
+    pub fn center(&self) -> Option<(f32, f32)> {
+        self.normalized_position()
+            .map(|(x, y)| (x * 2.0 - 1.0, y * 2.0 - 1.0))
+    }
+
+    /// Predicts the position of the next sample, in the same coordinates as the samples, by carrying on from the last
+    /// two samples at the same velocity. Lets a renderer draw slightly ahead of the stylus to hide latency.
+    ///
+    /// This is a best-effort guess: it overshoots when the stylus slows down or turns, so anything drawn from it should
+    /// be replaced once the real sample arrives. Returns `None` until at least two samples have been buffered.
+    #[cfg(feature = "history")]
+    pub fn predicted_next(&self) -> Option<(f32, f32)> {
+        predicted_next(&self.history.borrow())
+    }
+
+    /// Estimates where the stylus was, or will be, at the given time, in the same coordinates as the samples.
+    ///
+    /// The position is interpolated between the buffered samples either side of `t`, or extrapolated from the oldest
+    /// or newest two when `t` is outside them. Useful for compensating for input latency.
+    ///
+    /// Returns `None` if no samples have been received, or `t` is too far outside the buffered ones to guess.
+    #[cfg(feature = "history")]
+    pub fn position_at(&self, t: Instant) -> Option<(f32, f32)> {
+        position_at(&self.history.borrow(), t)
+    }
+}
+
+/// Disables the tablet when dropped. Returned by [`EasyTablet::enable_guard`].
+#[must_use = "the tablet is disabled again as soon as the guard is dropped"]
+pub struct EnableGuard<'a> {
+    tablet: &'a EasyTablet,
+}
+
+impl Drop for EnableGuard<'_> {
+    fn drop(&mut self) {
+        // there's nowhere to report the error to, and the stylus is unusable if it can't be disabled anyway
+        let _ = self.tablet.set_enabled(false);
+    }
+}
+
+impl Drop for __InnerTablet {
+    fn drop(&mut self) {
+        self.stop_expiry_timer();
+        self.stop_retry_timer();
+
+        let suppressed = self.suppressed_gestures.get();
+
+        if suppressed != 0 {
+            set_tablet_service_flags(self.hwnd.get(), suppressed, false);
+        }
+
+        // the stylus can outlive the tablet when something else holds on to it, so it has to stop calling the plugin
+        let _ = unsafe { self.stylus.SetEnabled(false) };
+
+        let plugin = match self.plugin.take() {
+            Some(plugin) => plugin,
+            None => return,
+        };
+
+        // other plugins may have been added in front of it since
+        let count = unsafe { self.stylus.GetStylusAsyncPluginCount() }.unwrap_or(0);
+        let index = (0..count).find(|&i| {
+            unsafe { self.stylus.GetStylusAsyncPlugin(i) }.ok().as_ref() == Some(&plugin)
+        });
+
+        if let Some(index) = index {
+            let mut removed = None;
+            let _ = unsafe { self.stylus.RemoveStylusAsyncPlugin(index, &mut removed) };
+        }
+    }
+}
+
+impl TabletBackend for EasyTablet {
+    fn open(hwnd: usize, opts: EasyTabOptions) -> EasyTabResult<Self> {
+        EasyTablet::init_options(HANDLE_PTR(hwnd), opts)
+    }
+
+    fn enable(&self) -> EasyTabResult<()> {
+        EasyTablet::enable(self)
+    }
+
+    fn disable(&self) -> EasyTabResult<()> {
+        EasyTablet::disable(self)
+    }
+
+    fn add_listener(&self, cb: Box<dyn Fn(WinTabEvent)>) -> ListenerId {
+        EasyTablet::add_listener(self, cb)
+    }
+
+    fn remove_listener(&self, id: ListenerId) -> bool {
+        EasyTablet::remove_listener(self, id)
+    }
+}
+
+// TODO: Convert to trait?
+impl __InnerTablet {
+    // maps a point in tablet coordinates to screen coordinates, in dips with `report_dips`
+    fn screen_point(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        self.physical_screen_point(x, y)
+            .map(|point| self.to_dips(point))
+    }
+
+    // maps a point in tablet coordinates to the client coordinates of the bound window, in dips with `report_dips`
+    fn window_point(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (x, y) = self.physical_screen_point(x, y)?;
+        let mut point = POINT { x, y };
+
+        unsafe { ScreenToClient(self.hwnd.get(), &mut point) };
+
+        Some(self.to_dips((point.x, point.y)))
+    }
+
+    // maps a point in client coordinates of the bound window to screen coordinates, both in dips with `report_dips`
+    fn client_to_screen(&self, point: (i32, i32)) -> (i32, i32) {
+        let (x, y) = self.to_pixels(point);
+        let mut point = POINT { x, y };
+
+        unsafe { ClientToScreen(self.hwnd.get(), &mut point) };
+
+        self.to_dips((point.x, point.y))
+    }
+
+    // maps a point in screen coordinates to client coordinates of the bound window, both in dips with `report_dips`
+    fn screen_to_client(&self, point: (i32, i32)) -> (i32, i32) {
+        let (x, y) = self.to_pixels(point);
+        let mut point = POINT { x, y };
+
+        unsafe { ScreenToClient(self.hwnd.get(), &mut point) };
+
+        self.to_dips((point.x, point.y))
+    }
+
+    // scales a point in physical pixels to dips when `report_dips` is set
+    fn to_dips(&self, point: (i32, i32)) -> (i32, i32) {
+        self.scale_dips(point, false)
+    }
+
+    // scales a point in dips back to physical pixels when `report_dips` is set
+    fn to_pixels(&self, point: (i32, i32)) -> (i32, i32) {
+        self.scale_dips(point, true)
+    }
+
+    // converts a point between physical pixels and dips, using the dpi of the bound window
+    fn scale_dips(&self, (x, y): (i32, i32), inverse: bool) -> (i32, i32) {
+        let dpi = self.dpi.get();
+
+        if !self.opts.report_dips || dpi == USER_DEFAULT_SCREEN_DPI {
+            return (x, y);
+        }
+
+        let scale = if inverse {
+            dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32
+        } else {
+            USER_DEFAULT_SCREEN_DPI as f32 / dpi as f32
+        };
+
+        (
+            (x as f32 * scale).round() as i32,
+            (y as f32 * scale).round() as i32,
+        )
+    }
+
+    // converts a sample's position into `opts.emit_coordinate_space`
+    fn to_emit_space(&self, sample: &mut StylusSample) {
+        let point = match self.opts.emit_coordinate_space {
+            CoordinateSpace::Tablet => return,
+            CoordinateSpace::Screen => self.screen_point(sample.x, sample.y),
+            CoordinateSpace::Window => self.window_point(sample.x, sample.y),
+        };
+
+        if let Some((x, y)) = point {
+            (sample.x, sample.y) = (x, y);
+        }
+    }
+
+    // maps a point in tablet coordinates to screen coordinates, in physical pixels
+    fn physical_screen_point(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        // the samples have already been mapped
+        if self.coordinate_mapper.borrow().is_some() {
+            return Some((x, y));
+        }
+
+        let (nx, ny) = self.sample_bounds()?.normalize(x, y);
+        let (nx, ny) = (nx.clamp(0.0, 1.0), ny.clamp(0.0, 1.0));
+
+        let target = if self.opts.map_to_window {
+            self.client_rect().ok()?
+        } else {
+            self.mapping_target.get()
+        };
+
+        Some((
+            target.left + (nx * target.width() as f32) as i32,
+            target.top + (ny * target.height() as f32) as i32,
+        ))
+    }
+
+    // the client area of the bound window, in screen coordinates
+    fn client_rect(&self) -> EasyTabResult<Rect> {
+        let hwnd = self.hwnd.get();
+        let mut rect = RECT::default();
+
+        unsafe { GetClientRect(hwnd, &mut rect).ok().map_err(ERROR_FN)? };
+
+        let mut top_left = POINT {
+            x: rect.left,
+            y: rect.top,
+        };
+        let mut bottom_right = POINT {
+            x: rect.right,
+            y: rect.bottom,
+        };
+
+        unsafe {
+            ClientToScreen(hwnd, &mut top_left).ok().map_err(ERROR_FN)?;
+            ClientToScreen(hwnd, &mut bottom_right)
+                .ok()
+                .map_err(ERROR_FN)?;
+        }
+
+        Ok(Rect {
+            left: top_left.x,
+            top: top_left.y,
+            right: bottom_right.x,
+            bottom: bottom_right.y,
+        })
+    }
+
+    // the GUIDs of a cursor's buttons, in the order the device reports them
+    fn cursor_buttons(&self, cid: u32) -> EasyTabResult<Vec<GUID>> {
+        let buttons = unsafe {
+            self.stylus
+                .GetStylusForId(cid)
+                .and_then(|cursor| cursor.Buttons())
+                .map_err(ERROR_FN)?
+        };
+
+        let count = unsafe { buttons.Count().map_err(ERROR_FN)? };
+
+        (0..count)
+            .map(|i| {
+                let id = unsafe {
+                    buttons
+                        .Item(&variant_i4(i))
+                        .and_then(|button| button.Id())
+                        .map_err(ERROR_FN)?
+                };
+
+                parse_guid(&id.to_string())
+            })
+            .collect()
+    }
+
+    // emits a `ButtonChanged` for a button GUID, if it's one of the cursor's buttons
+    fn handle_button(&self, cid: u32, guid: &GUID, pressed: bool, point: &POINT) -> Result<()> {
+        let index = self
+            .cursor_buttons(cid)
+            .ok()
+            .and_then(|buttons| buttons.iter().position(|button| button == guid));
+
+        if let Some(index) = index {
+            self.handle_event(WinTabEvent::ButtonChanged {
+                index,
+                pressed,
+                x: point.x,
+                y: point.y,
+            })?;
+        }
+
+        #[cfg(feature = "gestures")]
+        self.recognise_air_tap(cid, pressed, point)?;
+
+        Ok(())
+    }
+
+    // checks whether a button press or release ends a click made while the stylus hovers, emitting `AirTap`
+    #[cfg(feature = "gestures")]
+    fn recognise_air_tap(&self, cid: u32, pressed: bool, point: &POINT) -> Result<()> {
+        let hovering = !self.contacts.borrow().contains_key(&cid);
+
+        if pressed {
+            self.air_press.set(hovering.then(Instant::now));
+
+            return Ok(());
+        }
+
+        match self.air_press.take() {
+            Some(at) if hovering && at.elapsed() <= AIR_TAP_INTERVAL => {
+                self.handle_event(WinTabEvent::AirTap {
+                    x: point.x,
+                    y: point.y,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // turns off windows' actions for system gestures on the bound window, until the tablet is dropped
+    fn suppress_gestures(&self, flags: u32) {
+        let added = flags & !self.suppressed_gestures.get();
+
+        if added != 0 && set_tablet_service_flags(self.hwnd.get(), added, true) {
+            self.suppressed_gestures
+                .set(self.suppressed_gestures.get() | added);
+        }
+    }
+
+    // handles a stylus event
+    fn handle_event(&self, event: WinTabEvent) -> Result<()> {
+        self.dispatch(event).map(|_| ())
+    }
+
+    // handles a stylus event, returning whether any listener handled it
+    fn dispatch(&self, event: WinTabEvent) -> Result<EventResponse> {
+        debug_assert_eq!(
+            std::thread::current().id(),
+            self.thread,
+            "tablet events must be dispatched on the thread the tablet was created on"
+        );
+
+        // an event raised from inside a listener (e.g. by calling `enable`) is handled once the current one is done,
+        // so listeners always see events in order
+        if self.dispatching.replace(true) {
+            self.deferred.borrow_mut().push_back(event);
+
+            return Ok(EventResponse::Continue);
+        }
+
+        let mut result = self.dispatch_now(event);
+
+        loop {
+            // the borrow has to end before dispatching, as the listeners may defer more events
+            let next = self.deferred.borrow_mut().pop_front();
+
+            match next {
+                Some(next) => {
+                    if let Err(e) = self.dispatch_now(next) {
+                        result = result.and(Err(e));
+                    }
+                }
+                None => break,
+            }
+        }
+
+        self.dispatching.set(false);
+
+        result
+    }
+
+    // keeps the statistics of the stroke in progress up to date, for `last_stroke_stats`
+    #[cfg(feature = "strokes")]
+    fn track_stroke(&self, event: &WinTabEvent) {
+        let mut current = self.current_stroke.borrow_mut();
+
+        match event {
+            WinTabEvent::StylusActive => *current = Some(StatsBuilder::new(Instant::now())),
+
+            WinTabEvent::StylusMove(sample) if self.is_primary(self.cursor_id.get()) => {
+                if let Some(stroke) = current.as_mut() {
+                    stroke.push(sample);
+                }
+            }
+
+            WinTabEvent::StylusInactive => {
+                if let Some(stats) = current.take().and_then(|s| s.finish(Instant::now())) {
+                    self.last_stroke.set(Some(stats));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    // updates the tablet state and calls the listeners
+    fn dispatch_now(&self, event: WinTabEvent) -> Result<EventResponse> {
+        let event = match self.event_transform.borrow().as_ref() {
+            Some(transform) => match transform(event) {
+                Some(event) => event,
+                None => return Ok(EventResponse::Continue),
+            },
+            None => event,
+        };
+
+        #[cfg(feature = "strokes")]
+        self.track_stroke(&event);
+
+        match event {
+            WinTabEvent::StylusActive => self.active.set(true),
+            WinTabEvent::StylusInactive => self.active.set(false),
+
+            WinTabEvent::StylusButtonDown(x, y)
+            | WinTabEvent::StylusButtonUp(x, y)
+            | WinTabEvent::ButtonChanged { x, y, .. } => {
+                self.x.set(x);
+                self.y.set(y);
+            }
+
+            // with several contacts down, the getters follow the primary one
+            WinTabEvent::StylusMove(_) if !self.is_primary(self.cursor_id.get()) => {}
+
+            WinTabEvent::StylusMove(sample) | WinTabEvent::HoverMove(sample) => {
+                self.x.set(sample.x);
+                self.y.set(sample.y);
+                self.z.set(sample.z);
+                self.pressure.set(sample.pressure);
+                self.last_sample.set(Some(sample));
+
+                #[cfg(feature = "history")]
+                {
+                    let mut history = self.history.borrow_mut();
+
+                    if history.len() == HISTORY_CAPACITY {
+                        history.pop_front();
+                    }
+                    history.push_back((Instant::now(), sample));
+                }
+            }
+
+            WinTabEvent::Warning(_)
+            | WinTabEvent::TabletChanged
+            | WinTabEvent::Error(_)
+            | WinTabEvent::MappingChanged
+            | WinTabEvent::Ready
+            | WinTabEvent::PressureChanged(_)
+            | WinTabEvent::PressureAbove(_)
+            | WinTabEvent::PressureBelow(_)
+            | WinTabEvent::MaxPressure
+            | WinTabEvent::RelativeMove { .. }
+            | WinTabEvent::DoubleTap { .. }
+            | WinTabEvent::AirTap { .. }
+            | WinTabEvent::SystemGesture { .. }
+            | WinTabEvent::EnterWindow
+            | WinTabEvent::LeaveWindow => {}
+        }
+
+        {
+            let mut queue = self.queue.borrow_mut();
+
+            if queue.len() == QUEUE_CAPACITY {
+                queue.pop_front();
+            }
+            queue.push_back(event);
+        }
+
+        // the listeners are called from a copy of the list, as they may add or remove listeners (e.g. by detaching a
+        // `StrokeRecorder`). listeners added in the meantime get the next event, and removed ones aren't called again.
+        let listeners = self.listeners.borrow().clone();
+
+        let response = match call_listeners(&listeners, event, |id| {
+            self.listeners.borrow().iter().any(|l| l.id == id)
+        }) {
+            // the threaded listeners come after these, so they're skipped too
+            EventResponse::Stop => return Ok(EventResponse::Handled),
+            response => response,
+        };
+
+        #[cfg(feature = "dispatch-thread")]
+        if let Some(dispatcher) = &self.dispatcher {
+            let _ = dispatcher.send(DispatcherMessage::Event(event));
+        }
+
+        Ok(response)
+    }
+
+    // the region of the tablet that is mapped onto the screen
+    fn mapping_bounds(&self) -> Option<TabletBounds> {
+        self.active_area
+            .get()
+            .map(TabletBounds::from)
+            .or_else(|| self.bounds.get())
+    }
+
+    // the region the samples are spread over, in `opts.emit_coordinate_space`
+    fn emit_bounds(&self) -> Option<TabletBounds> {
+        let bounds = self.sample_bounds()?;
+
+        let ((min_x, min_y), (max_x, max_y)) = match self.opts.emit_coordinate_space {
+            CoordinateSpace::Tablet => return Some(bounds),
+            CoordinateSpace::Screen => (
+                self.screen_point(bounds.min_x, bounds.min_y)?,
+                self.screen_point(bounds.max_x, bounds.max_y)?,
+            ),
+            CoordinateSpace::Window => (
+                self.window_point(bounds.min_x, bounds.min_y)?,
+                self.window_point(bounds.max_x, bounds.max_y)?,
+            ),
+        };
+
+        Some(TabletBounds {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        })
+    }
+
+    // the range of the positions in the samples: the logical space when there is one, otherwise the mapped part of the
+    // tablet
+    fn sample_bounds(&self) -> Option<TabletBounds> {
+        match self.opts.logical_size {
+            Some((width, height)) => Some(TabletBounds {
+                min_x: 0,
+                min_y: 0,
+                max_x: width as i32,
+                max_y: height as i32,
+            }),
+            None => self.mapping_bounds(),
+        }
+    }
+
+    // works out the screen-space rect the tablet maps onto, restricting the stylus to it where possible
+    fn update_mapping(&self) -> Result<()> {
+        let monitor = match self.opts.target_monitor {
+            Some(index) => {
+                let monitor = monitor_rects().get(index).copied();
+
+                if monitor.is_none() {
+                    self.handle_event(WinTabEvent::Warning(Warning::InvalidMonitor(index)))?;
+                }
+
+                monitor
+            }
+            None => None,
+        };
+
+        if let Some(monitor) = monitor {
+            let hwnd = self.hwnd.get();
+            let mut top_left = POINT {
+                x: monitor.left,
+                y: monitor.top,
+            };
+            let mut bottom_right = POINT {
+                x: monitor.right,
+                y: monitor.bottom,
+            };
+
+            unsafe {
+                ScreenToClient(hwnd, &mut top_left);
+                ScreenToClient(hwnd, &mut bottom_right);
+            }
+
+            let input = RECT {
+                left: top_left.x,
+                top: top_left.y,
+                right: bottom_right.x,
+                bottom: bottom_right.y,
+            };
+
+            // not every driver supports restricting the input region, in which case the mapping still applies
+            let _ = unsafe { self.stylus.SetWindowInputRectangle(&input) };
+        }
+
+        let os_mapping = if monitor.is_none() && self.opts.use_os_mapping {
+            let name = self
+                .current_tablet_context()
+                .ok()
+                .flatten()
+                .and_then(|tcid| unsafe { self.stylus.GetTabletFromTabletContextId(tcid).ok() })
+                .and_then(|tablet| unsafe { tablet.Name().ok() })
+                .map(|name| name.to_string());
+
+            os_pen_mapping(name.as_deref())
+        } else {
+            None
+        };
+
+        self.mapping_target
+            .set(monitor.or(os_mapping).unwrap_or_else(virtual_screen_rect));
+        self.client_rect.set(self.client_rect().ok());
+        self.dpi.set(window_dpi(self.hwnd.get()));
+
+        Ok(())
+    }
+
+    // drops the cached packet layouts and re-reads the current tablet's, along with the bounds and capabilities
+    // derived from it
+    fn reread_description(&self) -> Result<()> {
+        self.packet_descriptions.borrow_mut().clear();
+
+        if let Some(tcid) = self.current_tablet_context()? {
+            self.bounds.set(self.packet_description(tcid)?.bounds());
+        }
+
+        self.capabilities.set(self.read_capabilities()?);
+
+        Ok(())
+    }
+
+    // re-reads everything the coordinate mapping is derived from, after the tablet-to-screen mapping changed
+    fn refresh_mapping(&self) -> Result<()> {
+        // the layout can change along with the mapping (e.g. after the driver reconfigures the tablet)
+        self.reread_description()?;
+        self.update_mapping()?;
+
+        self.handle_event(WinTabEvent::MappingChanged)
+    }
+
+    // stops the timer for the next retry scheduled by `schedule_retry`
+    fn stop_retry_timer(&self) {
+        let id = self.retry_timer.replace(0);
+
+        if id != 0 {
+            unsafe { KillTimer(HWND::default(), id) };
+
+            // the timers may already be gone if the tablet is dropped while the thread exits
+            let _ = RETRY_TIMERS.try_with(|timers| timers.borrow_mut().remove(&id));
+        }
+    }
+
+    // re-reads everything derived from the tablet, which may have changed since initialisation, once input starts
+    fn reconcile(&self) -> Result<()> {
+        self.reread_description()?;
+        self.update_mapping()?;
+
+        self.handle_event(WinTabEvent::Ready)
+    }
+
+    // describes a tablet for `on_connect`. the fields the driver doesn't report are left empty.
+    fn tablet_info(tablet: &IInkTablet) -> TabletInfo {
+        unsafe {
+            TabletInfo {
+                name: tablet.Name().map(|n| n.to_string()).unwrap_or_default(),
+                plug_and_play_id: tablet
+                    .PlugAndPlayId()
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            }
+        }
+    }
+
+    // caches the packet layout of a tablet
+    fn read_tablet(&self, tablet: &IInkTablet) -> Result<()> {
+        let tcid = unsafe { self.stylus.GetTabletContextIdFromTablet(tablet)? };
+        let description = Rc::new(self.read_description(tcid)?);
+
+        self.packet_descriptions
+            .borrow_mut()
+            .insert(tcid, description);
+
+        Ok(())
+    }
+
+    // the tablet context that last sent packets, or the first one the stylus knows about if none have yet
+    fn current_tablet_context(&self) -> Result<Option<u32>> {
+        if let Some(tcid) = self.tablet_context.get() {
+            return Ok(Some(tcid));
+        }
+
+        Ok(self.tablet_contexts()?.first().copied())
+    }
+
+    // every tablet context the stylus knows about, in the order the tablets are enumerated
+    fn tablet_contexts(&self) -> Result<Vec<u32>> {
+        let mut count = 0u32;
+        let mut raw: *mut u32 = std::ptr::null_mut();
+
+        unsafe { self.stylus.GetAllTabletContextIds(&mut count, &mut raw)? };
+
+        if raw.is_null() {
+            return Ok(Vec::new());
+        }
+
+        // the array is allocated by the stylus
+        let tcids = unsafe { std::slice::from_raw_parts(raw, count as usize) }.to_vec();
+        unsafe { CoTaskMemFree(raw as *const _) };
+
+        Ok(tcids)
+    }
+
+    // reads the capabilities of the current tablet, or `None` if there isn't one
+    fn read_capabilities(&self) -> Result<Option<Capabilities>> {
+        let tcid = match self.current_tablet_context()? {
+            Some(tcid) => tcid,
+            None => return Ok(None),
+        };
+
+        let tablet = unsafe { self.stylus.GetTabletFromTabletContextId(tcid)? };
+
+        // properties are named by their GUID in braces
+        let supported = |guid: &GUID| unsafe {
+            tablet
+                .IsPacketPropertySupported(&BSTR::from(format!("{{{:?}}}", guid)))
+                .map(|supported| supported != 0)
+                .unwrap_or(false)
+        };
+
+        let multi_touch = tablet
+            .cast::<IInkTablet3>()
+            .and_then(|tablet| unsafe { tablet.IsMultiTouch() })
+            .map(|multi_touch| multi_touch != 0)
+            .unwrap_or(false);
+
+        // no cursor has interacted with the tablet until the first packet arrives
+        let cursor = unsafe { self.stylus.GetStylusForId(self.cursor_id.get()) };
+        let buttons = cursor
+            .as_ref()
+            .ok()
+            .and_then(|cursor| unsafe { cursor.Buttons().and_then(|b| b.Count()).ok() })
+            .unwrap_or(0);
+        let inverted = cursor
+            .and_then(|cursor| unsafe { cursor.Inverted() })
+            .map(|inverted| inverted != 0)
+            .unwrap_or(false);
+
+        Ok(Some(Capabilities {
+            pressure: supported(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE),
+            tilt: supported(&GUID_PACKETPROPERTY_GUID_X_TILT_ORIENTATION)
+                || supported(&GUID_PACKETPROPERTY_GUID_AZIMUTH_ORIENTATION),
+            twist: supported(&GUID_PACKETPROPERTY_GUID_TWIST_ORIENTATION),
+            rotation: supported(&GUID_PACKETPROPERTY_GUID_YAW_ROTATION)
+                || supported(&GUID_PACKETPROPERTY_GUID_PITCH_ROTATION)
+                || supported(&GUID_PACKETPROPERTY_GUID_ROLL_ROTATION),
+            z: supported(&GUID_PACKETPROPERTY_GUID_Z),
+            eraser: inverted || self.capabilities.get().is_some_and(|c| c.eraser),
+            multi_touch,
+            buttons: buttons.max(0) as u32,
+            air_tap: buttons > 0,
+        }))
+    }
+
+    // reads the packet layout for a tablet context, along with the quirks of the tablet
+    fn read_description(&self, tcid: u32) -> Result<PacketDescription> {
+        #[allow(unused_mut)]
+        let mut description = PacketDescription::read(&self.stylus, tcid)?;
+
+        #[cfg(feature = "quirks")]
+        if !self.opts.disable_quirks {
+            let name =
+                unsafe { self.stylus.GetTabletFromTabletContextId(tcid)?.Name()? }.to_string();
+
+            description.quirks = quirks_for(&name);
+        }
+
+        Ok(description)
+    }
+
+    // returns the (cached) packet layout for a tablet context
+    fn packet_description(&self, tcid: u32) -> Result<Rc<PacketDescription>> {
+        if let Some(description) = self.packet_descriptions.borrow().get(&tcid) {
+            return Ok(Rc::clone(description));
+        }
+
+        let description = Rc::new(self.read_description(tcid)?);
+        self.packet_descriptions
+            .borrow_mut()
+            .insert(tcid, Rc::clone(&description));
+
+        Ok(description)
+    }
+
+    // decodes a buffer of packets, emitting an event for each one.
+    // the packets are handled if any of their events were.
+    // handles packets straight from a stylus callback, or copies them to decode later with `lazy_decode`
+    fn receive_packets(
+        &self,
+        info: &StylusInfo,
+        packets: &[i32],
+        event: fn(StylusSample) -> WinTabEvent,
+    ) -> Result<EventResponse> {
+        let received = (Instant::now(), message_time());
+
+        if !self.opts.lazy_decode {
+            return self.handle_packets(info, packets, event, received);
+        }
+
+        // the buffer belongs to the stylus and is only valid during the callback
+        self.pending_packets.borrow_mut().push_back(PendingPackets {
+            info: *info,
+            packets: packets.to_vec(),
+            event,
+            received,
+        });
+
+        Ok(EventResponse::Continue)
+    }
+
+    // decodes the packets copied by `receive_packets`, oldest first
+    fn decode_pending(&self) -> Result<()> {
+        loop {
+            let next = self.pending_packets.borrow_mut().pop_front();
+
+            match next {
+                Some(pending) => {
+                    self.handle_packets(
+                        &pending.info,
+                        &pending.packets,
+                        pending.event,
+                        pending.received,
+                    )?;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn handle_packets(
+        &self,
+        info: &StylusInfo,
+        packets: &[i32],
+        event: fn(StylusSample) -> WinTabEvent,
+        (instant, os_time): (Instant, u32),
+    ) -> Result<EventResponse> {
+        self.flush_pending_up(false)?;
+        self.expire_contacts()?;
+
+        self.cursor_id.set(info.cid);
+        self.tablet_context.set(Some(info.tcid));
+
+        let description = self.packet_description(info.tcid)?;
+        self.bounds.set(description.bounds());
+
+        if description.stride() == 0 {
+            return Ok(EventResponse::Continue);
+        }
+
+        let mut response = EventResponse::Continue;
+
+        // whether these packets are from a contact that is down, rather than hovering
+        let down = matches!(event(StylusSample::default()), WinTabEvent::StylusMove(_));
+
+        for packet in packets.chunks_exact(description.stride()) {
+            #[cfg(feature = "raw-packets")]
+            if let Some(cb) = self.raw_packet_callback.borrow().as_ref() {
+                cb(packet, &description.offsets);
+            }
+
+            let mut sample = self.decode(&description, packet, down);
+            sample.cursor = info.cid;
+            sample.input_source = self.contact_kind(info).into();
+            sample.instant = Some(instant);
+            sample.os_time = Some(os_time);
+
+            // the window is tracked in tablet coordinates, as the client area is in screen coordinates
+            if self.is_primary(info.cid) {
+                self.track_window(&sample)?;
+            }
+
+            self.to_emit_space(&mut sample);
+            self.track_cursor(info, |state| state.last_sample = Some(sample));
+
+            let event = event(sample);
+
+            if let WinTabEvent::StylusMove(sample) = event {
+                if let Some(contact) = self.contacts.borrow_mut().get_mut(&info.cid) {
+                    contact.x = sample.x;
+                    contact.y = sample.y;
+                    contact.pressure = sample.pressure;
+                    contact.size = sample.contact_size;
+
+                    self.contact_seen
+                        .borrow_mut()
+                        .insert(info.cid, Instant::now());
+                }
+            }
+
+            // a contact that started inside the deadzone only counts once the pressure gets past it
+            if let WinTabEvent::StylusMove(sample) = event {
+                #[cfg(feature = "filters")]
+                if !self.active.get() && self.opts.pressure_deadzone > 0.0 && sample.pressure > 0.0
+                {
+                    self.handle_event(WinTabEvent::StylusActive)?;
+                }
+
+                #[cfg(feature = "filters")]
+                if let Some(threshold) = self.opts.pressure_change_threshold {
+                    if (sample.pressure - self.reported_pressure.get()).abs() > threshold {
+                        self.reported_pressure.set(sample.pressure);
+                        self.handle_event(WinTabEvent::PressureChanged(sample.pressure))?;
+                    }
+                }
+
+                #[cfg(feature = "filters")]
+                if let Some(threshold) = self.opts.pressure_threshold {
+                    let crossing = threshold_crossing(
+                        self.above_threshold.get(),
+                        sample.pressure,
+                        threshold,
+                        self.opts.pressure_hysteresis,
+                    );
+
+                    if let Some(above) = crossing {
+                        self.above_threshold.set(above);
+                        self.handle_event(if above {
+                            WinTabEvent::PressureAbove(sample.pressure)
+                        } else {
+                            WinTabEvent::PressureBelow(sample.pressure)
+                        })?;
+                    }
+                }
+
+                if bottomed_out(&self.max_pressure_reached, sample.pressure) {
+                    self.handle_event(WinTabEvent::MaxPressure)?;
+                }
+
+                #[cfg(feature = "filters")]
+                if self.opts.require_pressure_for_move && sample.pressure == 0.0 {
+                    continue;
+                }
+            }
+
+            #[cfg(feature = "filters")]
+            if let Some(hz) = self.opts.max_event_hz.filter(|hz| *hz > 0) {
+                let now = Instant::now();
+
+                if !under_rate_cap(hz, self.last_move_at.get(), now) {
+                    continue;
+                }
+
+                self.last_move_at.set(Some(now));
+            }
+
+            if self.dispatch(event)? == EventResponse::Handled {
+                response = EventResponse::Handled;
+            }
+
+            if self.opts.relative_mode {
+                let position = (info.cid, sample.x, sample.y);
+                let origin = self.relative_origin.replace(Some(position));
+
+                if let Some((dx, dy)) = relative_delta(origin, position) {
+                    self.handle_event(WinTabEvent::RelativeMove { dx, dy })?;
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
+    // emits `EnterWindow` or `LeaveWindow` when a sample crosses the edge of the bound window's client area
+    fn track_window(&self, sample: &StylusSample) -> Result<()> {
+        let inside = self
+            .client_rect
+            .get()
+            .zip(self.physical_screen_point(sample.x, sample.y))
+            .map(|(rect, (x, y))| rect.contains(x, y));
+
+        match inside {
+            Some(inside) if inside != self.in_window.replace(inside) => {
+                self.handle_event(if inside {
+                    WinTabEvent::EnterWindow
+                } else {
+                    WinTabEvent::LeaveWindow
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // decodes a single packet, applying the options
+    fn decode(&self, description: &PacketDescription, packet: &[i32], down: bool) -> StylusSample {
+        let mut sample = description.sample(packet);
+        let range = self.pressure_range.get();
+
+        if let Some((min, max)) = range {
+            if let Some(p) = description.value(packet, &GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE) {
+                sample.pressure = ((p - min) as f32 / (max - min) as f32).clamp(0.0, 1.0);
+            }
+        }
+
+        #[cfg(feature = "quirks")]
+        for quirk in &description.quirks {
+            match *quirk {
+                Quirk::InvertedTiltY => {
+                    if let Some((x, y)) = sample.tilt {
+                        sample.tilt = Some((x, -y));
+                        sample.azimuth = sample.azimuth.map(|a| (360.0 - a).rem_euclid(360.0));
+                    }
+                }
+                // the override already replaces the range
+                Quirk::PressureMax(_) if range.is_some() => {}
+                Quirk::PressureMax(max) => {
+                    if let Some(m) = description.metrics(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE)
+                    {
+                        if max > m.nLogicalMin {
+                            let scale = (m.nLogicalMax - m.nLogicalMin) as f32
+                                / (max - m.nLogicalMin) as f32;
+                            sample.pressure = (sample.pressure * scale).min(1.0);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(mapper) = self.coordinate_mapper.borrow().as_ref() {
+            (sample.x, sample.y) = mapper(sample.x, sample.y);
+        } else if let Some((width, height)) = self.opts.logical_size {
+            let bounds = self
+                .active_area
+                .get()
+                .map(TabletBounds::from)
+                .or_else(|| description.bounds());
+
+            if let Some(bounds) = bounds {
+                let (nx, ny) = bounds.normalize(sample.x, sample.y);
+                let scale = |n: f32, len: u32| {
+                    ((n.clamp(0.0, 1.0) * len as f32) as i32).min(len.max(1) as i32 - 1)
+                };
+
+                (sample.x, sample.y) = (scale(nx, width), scale(ny, height));
+            }
+        }
+
+        if self.opts.synth_pressure
+            && description
+                .offset(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE)
+                .is_none()
+        {
+            sample.pressure = if down { 1.0 } else { 0.0 };
+        }
+
+        #[cfg(feature = "filters")]
+        {
+            sample.pressure = apply_deadzone(sample.pressure, self.opts.pressure_deadzone);
+        }
+
+        #[cfg(feature = "histogram")]
+        if down && sample.pressure > 0.0 {
+            let bin = (sample.pressure * HISTOGRAM_BINS as f32) as usize;
+            self.pressure_histogram.borrow_mut()[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        sample
+    }
+
+    // handles the stylus touching the digitiser
+    fn handle_down(&self, info: &StylusInfo, packet: &[i32]) -> Result<()> {
+        // the contact going straight back down after lifting carries on as if it never lifted
+        if let Some((cid, at)) = self.pending_up.get() {
+            if cid == info.cid && self.debouncing(at) {
+                self.pending_up.set(None);
+
+                return Ok(());
+            }
+        }
+
+        self.flush_pending_up(true)?;
+        self.expire_contacts()?;
+
+        self.cursor_id.set(info.cid);
+        self.tablet_context.set(Some(info.tcid));
+
+        let description = self.packet_description(info.tcid)?;
+
+        let mut sample = if packet.len() >= description.stride() {
+            let mut sample = self.decode(&description, packet, true);
+            self.to_emit_space(&mut sample);
+
+            sample
+        } else {
+            StylusSample::default()
+        };
+        sample.cursor = info.cid;
+        sample.input_source = self.contact_kind(info).into();
+        sample.instant = Some(Instant::now());
+        sample.os_time = Some(message_time());
+
+        self.track_cursor(info, |state| {
+            state.down = true;
+            state.last_sample = Some(sample);
+        });
+
+        let mut contacts = self.contacts.borrow_mut();
+
+        if contacts.is_empty() {
+            self.primary_contact.set(Some(info.cid));
+        }
+
+        #[cfg(feature = "gestures")]
+        self.tap_start
+            .set(Some((info.cid, Instant::now(), sample.x, sample.y)));
+        self.contact_seen
+            .borrow_mut()
+            .insert(info.cid, Instant::now());
+        self.max_pressure_reached.set(false);
+
+        contacts.insert(
+            info.cid,
+            Contact {
+                id: info.cid,
+                kind: self.contact_kind(info),
+                x: sample.x,
+                y: sample.y,
+                pressure: sample.pressure,
+                size: sample.contact_size,
+            },
+        );
+        drop(contacts);
+
+        // contacts too light to get past the deadzone are ignored until the pressure picks up
+        #[cfg(feature = "filters")]
+        if self.opts.pressure_deadzone > 0.0
+            && description
+                .offset(&GUID_PACKETPROPERTY_GUID_NORMAL_PRESSURE)
+                .is_some()
+            && packet.len() >= description.stride()
+            && sample.pressure == 0.0
+        {
+            return Ok(());
+        }
+
+        self.handle_event(WinTabEvent::StylusActive)
+    }
+
+    // handles a contact leaving the digitiser
+    fn handle_up(&self, cid: u32) -> Result<()> {
+        if self.opts.contact_debounce.is_some() {
+            // a different contact lifting in the meantime means the held back one isn't coming back
+            self.flush_pending_up(true)?;
+            self.pending_up.set(Some((cid, Instant::now())));
+
+            return Ok(());
+        }
+
+        self.lift_contact(cid)
+    }
+
+    // whether a lift at the given time is still within `opts.contact_debounce`
+    fn debouncing(&self, at: Instant) -> bool {
+        within_debounce(self.opts.contact_debounce, at, Instant::now())
+    }
+
+    // reports the lift held back by `opts.contact_debounce`, once the contact hasn't gone back down in time (or
+    // straight away if forced)
+    fn flush_pending_up(&self, force: bool) -> Result<()> {
+        match self.pending_up.get() {
+            Some((cid, at)) if force || !self.debouncing(at) => self.lift_contact(cid),
+            _ => Ok(()),
+        }
+    }
+
+    // lifts a contact, finishing its stroke
+    fn lift_contact(&self, cid: u32) -> Result<()> {
+        if matches!(self.pending_up.get(), Some((pending, _)) if pending == cid) {
+            self.pending_up.set(None);
+        }
+
+        if let Some(state) = self.cursors.borrow_mut().get_mut(&cid) {
+            state.down = false;
+        }
+
+        // there may not be a packet with zero pressure before the contact lifts
+        #[cfg(feature = "filters")]
+        if self.above_threshold.replace(false) {
+            self.handle_event(WinTabEvent::PressureBelow(0.0))?;
+        }
+
+        #[cfg_attr(not(feature = "gestures"), allow(unused_variables))]
+        let contact = self.contacts.borrow_mut().remove(&cid);
+        self.contact_seen.borrow_mut().remove(&cid);
+
+        #[cfg(feature = "gestures")]
+        if let Some(contact) = contact {
+            self.recognise_tap(cid, contact.x, contact.y)?;
+        }
+
+        if self.primary_contact.get() == Some(cid) {
+            self.primary_contact.set(None);
+        }
+
+        self.handle_event(WinTabEvent::StylusInactive)
+    }
+
+    // handles a stylus leaving the range of the digitiser. a stylus pulled away quickly can leave without the tablet
+    // lifting its contact, so it's lifted here to finish the stroke.
+    fn handle_out_of_range(&self, cid: u32) -> Result<()> {
+        self.flush_pending_up(true)?;
+
+        if self.is_primary(cid) && self.in_window.replace(false) {
+            self.handle_event(WinTabEvent::LeaveWindow)?;
+        }
+
+        let down = self.contacts.borrow().contains_key(&cid);
+
+        if down {
+            self.lift_contact(cid)
+        } else {
+            self.handle_event(WinTabEvent::StylusInactive)
+        }
+    }
+
+    // stops the timer started by `watch_contacts`
+    fn stop_expiry_timer(&self) {
+        let id = self.expiry_timer.replace(0);
+
+        if id != 0 {
+            unsafe { KillTimer(HWND::default(), id) };
+
+            // the timers may already be gone if the tablet is dropped while the thread exits
+            let _ = EXPIRY_TIMERS.try_with(|timers| timers.borrow_mut().remove(&id));
+        }
+    }
+
+    // lifts the contacts that have gone quiet for longer than `opts.stroke_timeout`
+    fn expire_contacts(&self) -> Result<()> {
+        let timeout = match self.opts.stroke_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+
+        let expired: Vec<u32> = self
+            .contact_seen
+            .borrow()
+            .iter()
+            .filter(|(_, seen)| seen.elapsed() > timeout)
+            .map(|(&cid, _)| cid)
+            .collect();
+
+        for cid in expired {
+            self.lift_contact(cid)?;
+        }
+
+        Ok(())
+    }
+
+    // checks whether a contact lifting at the given position ends a tap, emitting `DoubleTap` for the second of a pair
+    #[cfg(feature = "gestures")]
+    fn recognise_tap(&self, cid: u32, x: i32, y: i32) -> Result<()> {
+        let config = match self.opts.double_tap_config {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+
+        let is_tap = match self.tap_start.take() {
+            Some((start_cid, start, sx, sy)) => {
+                start_cid == cid && within_tap(&config, (start, sx, sy), (now, x, y))
+            }
+            None => false,
+        };
+
+        if !is_tap {
+            self.last_tap.set(None);
+
+            return Ok(());
+        }
+
+        match self.last_tap.take() {
+            Some(last) if within_tap(&config, last, (now, x, y)) => {
+                self.handle_event(WinTabEvent::DoubleTap { x, y })
+            }
+            // too slow or too far, so this tap could be the first of the next pair
+            _ => {
+                self.last_tap.set(Some((now, x, y)));
+
+                Ok(())
+            }
+        }
+    }
+
+    // whether a contact is the primary one, or there is no primary contact to follow
+    fn is_primary(&self, cid: u32) -> bool {
+        self.primary_contact
+            .get()
+            .is_none_or(|primary| primary == cid)
+    }
+
+    // updates the state kept for a cursor, adding it if it's new
+    fn track_cursor(&self, info: &StylusInfo, f: impl FnOnce(&mut CursorState)) {
+        let mut cursors = self.cursors.borrow_mut();
+
+        let state = cursors.entry(info.cid).or_insert_with(|| CursorState {
+            id: info.cid,
+            kind: self.contact_kind(info),
+            down: false,
+            last_sample: None,
+        });
+
+        f(state);
+    }
+
+    // works out what is touching the digitiser, defaulting to a pen when the tablet doesn't say
+    fn contact_kind(&self, info: &StylusInfo) -> ContactKind {
+        if info.bIsInvertedCursor.as_bool() {
+            if let Some(mut capabilities) = self.capabilities.get() {
+                capabilities.eraser = true;
+                self.capabilities.set(Some(capabilities));
+            }
+
+            return ContactKind::Eraser;
+        }
+
+        let kind = unsafe { self.stylus.GetTabletFromTabletContextId(info.tcid) }
+            .and_then(|tablet| tablet.cast::<IInkTablet2>())
+            .and_then(|tablet| unsafe { tablet.DeviceKind() });
+
+        match kind {
+            Ok(kind) if kind == TDK_Touch => ContactKind::Touch,
+            Ok(kind) if kind == TDK_Mouse => ContactKind::Mouse,
+            _ => ContactKind::Pen,
+        }
+    }
+}
+
+// the plugin added to the real time stylus to allow getting real time events from the stylus (asynchronously).
+// it only holds a weak reference, as the stylus holds on to the plugin, so a strong one would keep the tablet alive
+#[implement(IStylusAsyncPlugin)]
+struct AsyncStylusHandler(Weak<__InnerTablet>);
+
+// the tablet a handler belongs to, returning from the callback if the tablet is being dropped
+macro_rules! tablet {
+    ($handler:expr) => {
+        match $handler.0.upgrade() {
+            Some(tablet) => tablet,
+            None => return Ok(()),
+        }
+    };
+}
+
+impl IStylusPlugin_Impl for AsyncStylusHandler {
+    fn RealTimeStylusEnabled(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        _: u32,
+        _: *const u32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        // listeners are usually registered by now, so any mapping warnings reach them
+        tablet.reconcile()
+    }
+
+    fn RealTimeStylusDisabled(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        _: u32,
+        _: *const u32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        // no up events arrive while disabled, so the contacts would go stale
+        tablet.contacts.borrow_mut().clear();
+        tablet.contact_seen.borrow_mut().clear();
+        tablet.pending_up.set(None);
+        tablet.primary_contact.set(None);
+
+        for state in tablet.cursors.borrow_mut().values_mut() {
+            state.down = false;
+        }
+
+        tablet.handle_event(WinTabEvent::StylusInactive)
+    }
+
+    fn StylusInRange(&self, _: &Option<IRealTimeStylus>, _: u32, sid: u32) -> Result<()> {
+        let tablet = tablet!(self);
+
+        tablet.cursor_id.set(sid);
+        tablet.relative_origin.set(None);
+
+        // the window may have moved since the stylus was last around
+        let client_rect = tablet.client_rect().ok();
+        tablet.client_rect.set(client_rect);
+
+        Ok(())
+    }
+
+    fn StylusOutOfRange(&self, pirtssrc: &Option<IRealTimeStylus>, _: u32, sid: u32) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        tablet.decode_pending()?;
+        tablet.handle_out_of_range(sid)
+    }
+
+    fn StylusDown(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pstylusinfo: *const StylusInfo,
+        cpropcountperpkt: u32,
+        ppacket: *const i32,
+        _: *mut *mut i32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        // checking that the stylus receiving events is the same stylus the tablet is pointing to
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let (info, packet) = unsafe {
+            (
+                &*pstylusinfo,
+                std::slice::from_raw_parts(ppacket, cpropcountperpkt as usize),
+            )
+        };
+
+        // packets still waiting to be decoded came before this
+        tablet.decode_pending()?;
+        tablet.handle_down(info, packet)?;
+
+        watch_contacts(&tablet);
+
+        Ok(())
+    }
+
+    fn StylusUp(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pstylusinfo: *const StylusInfo,
+        _: u32,
+        _: *const i32,
+        _: *mut *mut i32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        tablet.decode_pending()?;
+        tablet.handle_up(unsafe { (*pstylusinfo).cid })
+    }
+
+    // TODO: test with more tablets - with my tablet, the GUID for the button doesnt seem to be any real, registered COM class.
+    fn StylusButtonDown(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        sid: u32,
+        pguidstylusbutton: *const GUID,
+        pstyluspos: *mut POINT,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let point = unsafe { &*pstyluspos };
+
+        tablet.handle_event(WinTabEvent::StylusButtonDown(point.x, point.y))?;
+
+        tablet.handle_button(sid, unsafe { &*pguidstylusbutton }, true, point)
+    }
+
+    fn StylusButtonUp(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        sid: u32,
+        pguidstylusbutton: *const GUID,
+        pstyluspos: *mut POINT,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let point = unsafe { &*pstyluspos };
+
+        tablet.handle_event(WinTabEvent::StylusButtonUp(point.x, point.y))?;
+
+        tablet.handle_button(sid, unsafe { &*pguidstylusbutton }, false, point)
+    }
+
+    fn InAirPackets(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pstylusinfo: *const StylusInfo,
+        cpktcount: u32,
+        cpktbufflength: u32,
+        ppackets: *const i32,
+        pcinoutpkts: *mut u32,
+        ppinoutpkts: *mut *mut i32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let (info, packets) = unsafe {
+            (
+                &*pstylusinfo,
+                std::slice::from_raw_parts(ppackets, cpktbufflength as usize),
+            )
+        };
+
+        let response = tablet.receive_packets(info, packets, WinTabEvent::HoverMove)?;
+
+        unsafe { consume_packets(response, ppackets, pcinoutpkts, ppinoutpkts) };
+
+        Ok(())
+    }
+
+    fn Packets(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pstylusinfo: *const StylusInfo,
+        cpktcount: u32,
+        cpktbufflength: u32,
+        ppackets: *const i32,
+        pcinoutpkts: *mut u32,
+        ppinoutpkts: *mut *mut i32,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let (info, packets) = unsafe {
+            (
+                &*pstylusinfo,
+                std::slice::from_raw_parts(ppackets, cpktbufflength as usize),
+            )
+        };
+
+        let response = tablet.receive_packets(info, packets, WinTabEvent::StylusMove)?;
+
+        unsafe { consume_packets(response, ppackets, pcinoutpkts, ppinoutpkts) };
+
+        Ok(())
+    }
+
+    fn CustomStylusDataAdded(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pguidid: *const GUID,
+        cbdata: u32,
+        pbdata: *const u8,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        if !pguidid.is_null() && unsafe { *pguidid } == FLUSH_MARKER {
+            tablet.flushed.set(true);
+        }
+
+        Ok(())
+    }
+
+    fn SystemEvent(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        _: u32,
+        _: u32,
+        event: u16,
+        eventdata: &SYSTEM_EVENT_DATA,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        let gesture = match system_gesture(event) {
+            Some(gesture) => gesture,
+            None => return Ok(()),
+        };
+
+        tablet.decode_pending()?;
+
+        let response = tablet.dispatch(WinTabEvent::SystemGesture {
+            gesture,
+            x: eventdata.xPos,
+            y: eventdata.yPos,
+        })?;
+
+        tablet.suppress_gestures(gesture_suppression(gesture, response));
+
+        Ok(())
+    }
+
+    fn TabletAdded(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        pitablet: &Option<IInkTablet>,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        tablet.capabilities.set(None);
+        tablet.device_ids.borrow_mut().clear();
+
+        let result = match pitablet {
+            Some(ink_tablet) if tablet.retry_on_change.get() => {
+                reinitialise(&tablet, ink_tablet, 0)
+            }
+            _ => Ok(()),
+        };
+
+        if let (Some(ink_tablet), Some(on_connect)) =
+            (pitablet, tablet.on_connect.borrow().as_ref())
+        {
+            on_connect(&__InnerTablet::tablet_info(ink_tablet));
+        }
+
+        result
+    }
+
+    fn TabletRemoved(&self, pirtssrc: &Option<IRealTimeStylus>, _: i32) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        // tablet context ids can be reused, so drop the layouts rather than risk decoding with a stale one
+        tablet.packet_descriptions.borrow_mut().clear();
+        tablet.device_ids.borrow_mut().clear();
+        tablet.capabilities.set(None);
+
+        if let Some(on_disconnect) = tablet.on_disconnect.borrow().as_ref() {
+            on_disconnect();
+        }
+
+        Ok(())
+    }
+
+    fn Error(
+        &self,
+        pirtssrc: &Option<IRealTimeStylus>,
+        piplugin: &Option<IStylusPlugin>,
+        datainterest: RealTimeStylusDataInterest,
+        hrerrorcode: HRESULT,
+        lptrkey: *mut isize,
+    ) -> Result<()> {
+        let tablet = tablet!(self);
+
+        // the stylus carries on after an error in a plugin, so let the listeners know about it
+        tablet.handle_event(WinTabEvent::Error(hrerrorcode))
+    }
+
+    fn UpdateMapping(&self, pirtssrc: &Option<IRealTimeStylus>) -> Result<()> {
+        let tablet = tablet!(self);
+
+        debug_assert!(pirtssrc.as_ref().unwrap() == &tablet.stylus);
+
+        // the display configuration changed (resolution, monitors...)
+        tablet.refresh_mapping()
+    }
+
+    fn DataInterest(&self) -> Result<RealTimeStylusDataInterest> {
+        // collect all data on stylus events
+        Ok(RTSDI_AllData)
+    }
+}
+
+// `IStylusAsyncPlugin_Impl` is created by the `implement` macro
+impl IStylusAsyncPlugin_Impl for AsyncStylusHandler {}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
+
+    use super::*;
+
+    #[test]
+    fn parses_guids_with_or_without_braces() {
+        let guid = GUID::from_u128(0x1234abcd_0001_0002_0003_00000000abcd);
+
+        assert_eq!(
+            parse_guid("{1234ABCD-0001-0002-0003-00000000ABCD}").unwrap(),
+            guid
+        );
+        assert_eq!(
+            parse_guid("1234abcd-0001-0002-0003-00000000abcd").unwrap(),
+            guid
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_guids() {
+        for s in [
+            "",
+            "{1234abcd-0001-0002-0003-00000000abc}",
+            "1234abcd00010002000300000000abcd",
+            "1234abcd-0001-0002-0003-00000000abcg",
+            "1234abcd-0001-0002-0003-00000000-abc",
+        ] {
+            assert!(
+                matches!(parse_guid(s), Err(EasyTabError::InvalidGuid(_))),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn handled_gestures_are_suppressed() {
+        assert_eq!(
+            gesture_suppression(SystemGesture::HoldEnter, EventResponse::Handled),
+            TABLET_DISABLE_PRESSANDHOLD
+        );
+        assert_eq!(
+            gesture_suppression(SystemGesture::Flick, EventResponse::Stop),
+            TABLET_DISABLE_FLICKS
+        );
+        assert_eq!(
+            gesture_suppression(SystemGesture::Flick, EventResponse::Continue),
+            0
+        );
+        assert_eq!(
+            gesture_suppression(SystemGesture::Tap, EventResponse::Handled),
+            0
+        );
+    }
+
+    #[test]
+    fn system_events_map_to_gestures() {
+        assert_eq!(
+            system_gesture(ISG_HoldEnter.0 as u16),
+            Some(SystemGesture::HoldEnter)
+        );
+        assert_eq!(
+            system_gesture(ISG_Flick.0 as u16),
+            Some(SystemGesture::Flick)
+        );
+        assert_eq!(system_gesture(0), None);
+    }
+
+    #[test]
+    fn handled_packets_are_consumed() {
+        let packets = [1, 2, 3];
+
+        for (response, consumed) in [
+            (EventResponse::Continue, false),
+            (EventResponse::Handled, true),
+            (EventResponse::Stop, true),
+        ] {
+            let mut count = 3;
+            let mut buffer = std::ptr::null_mut();
+
+            assert_eq!(
+                unsafe { consume_packets(response, packets.as_ptr(), &mut count, &mut buffer) },
+                consumed
+            );
+            assert_eq!(count == 0, consumed);
+            assert_eq!(buffer.is_null(), !consumed);
+        }
+    }
+
+    #[test]
+    fn driver_smoothing_sets_fit_to_curve() {
+        struct Attributes(Cell<Option<i16>>);
+
+        impl FitToCurve for Attributes {
+            fn set_fit_to_curve(&self, flag: i16) -> Result<()> {
+                self.0.set(Some(flag));
+
+                Ok(())
+            }
+        }
+
+        let attributes = Attributes(Cell::default());
+
+        apply_driver_smoothing(&attributes, true).unwrap();
+        assert_eq!(attributes.0.get(), Some(VARIANT_TRUE));
+
+        apply_driver_smoothing(&attributes, false).unwrap();
+        assert_eq!(attributes.0.get(), Some(VARIANT_FALSE));
+    }
+
+    #[cfg(feature = "quirks")]
+    #[test]
+    fn named_devices_get_their_quirks() {
+        assert_eq!(quirks_for("XP-Pen Artist 12 Pro"), [Quirk::InvertedTiltY]);
+        assert_eq!(quirks_for("Huion Kamvas 13"), [Quirk::PressureMax(8000)]);
+        assert_eq!(quirks_for("Wacom Intuos S"), []);
+    }
+
+    #[cfg(feature = "gestures")]
+    #[test]
+    fn taps_must_be_quick_and_close() {
+        let config = DoubleTapConfig {
+            interval: Duration::from_millis(400),
+            max_distance: 10,
+        };
+        let start = Instant::now();
+        let at = |ms, x, y| (start + Duration::from_millis(ms), x, y);
+
+        assert!(within_tap(&config, at(0, 0, 0), at(400, 6, 8)));
+        assert!(!within_tap(&config, at(0, 0, 0), at(401, 0, 0)));
+        assert!(!within_tap(&config, at(0, 0, 0), at(100, 6, 9)));
+    }
+
+    #[test]
+    fn tablets_shaped_like_a_monitor_are_integrated() {
+        let bounds = TabletBounds {
+            min_x: 0,
+            min_y: 0,
+            max_x: 29376,
+            max_y: 16524,
+        };
+        let monitor = |width: i32, height: i32| Rect {
+            left: -width,
+            top: 0,
+            right: 0,
+            bottom: height,
+        };
+
+        assert_eq!(
+            kind_from_bounds(&bounds, &[monitor(1280, 1024), monitor(1920, 1080)]),
+            TabletKind::Integrated
+        );
+        assert_eq!(
+            kind_from_bounds(&bounds, &[monitor(1280, 1024), monitor(1920, 1200)]),
+            TabletKind::External
+        );
+        // empty monitors are skipped
+        assert_eq!(
+            kind_from_bounds(&bounds, &[monitor(0, 0)]),
+            TabletKind::External
+        );
+    }
+
+    #[test]
+    fn tablet_kind_is_unknown_without_monitors_or_bounds() {
+        let bounds = TabletBounds {
+            min_x: 0,
+            min_y: 0,
+            max_x: 16000,
+            max_y: 9000,
+        };
+        let monitor = Rect {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1080,
+        };
+
+        assert_eq!(kind_from_bounds(&bounds, &[]), TabletKind::Unknown);
+        assert_eq!(
+            kind_from_bounds(&TabletBounds::default(), &[monitor]),
+            TabletKind::Unknown
+        );
+    }
+
+    #[cfg(feature = "histogram")]
+    #[test]
+    fn histogram_bins_merge_into_buckets() {
+        let mut bins = [1; HISTOGRAM_BINS];
+        bins[HISTOGRAM_BINS - 1] = 10;
+
+        assert_eq!(merge_histogram(&bins, 4), [64, 64, 64, 73]);
+        assert_eq!(merge_histogram(&bins, 1), [265]);
+        assert_eq!(
+            merge_histogram(&bins, HISTOGRAM_BINS)[HISTOGRAM_BINS - 1],
+            10
+        );
+
+        // 256 bins don't divide into 3, so the buckets get 86, 85 and 85 of them
+        assert_eq!(merge_histogram(&bins, 3), [86, 85, 94]);
+    }
+
+    #[cfg(feature = "histogram")]
+    #[test]
+    fn empty_histograms_merge_to_zero() {
+        assert_eq!(merge_histogram(&[0; HISTOGRAM_BINS], 4), [0; 4]);
+        assert!(merge_histogram(&[1; HISTOGRAM_BINS], 0).is_empty());
+    }
+
+    // adds a listener that logs its name, the way `add_filtered_listener` does
+    fn add_logging_listener(
+        listeners: &mut Vec<Listener>,
+        log: &Rc<RefCell<Vec<&'static str>>>,
+        name: &'static str,
+        mask: EventMask,
+        priority: i32,
+        response: EventResponse,
+    ) {
+        let log = log.clone();
+        let index = listener_index(listeners, priority);
+
+        listeners.insert(
+            index,
+            Listener {
+                id: ListenerId(listeners.len()),
+                mask,
+                priority,
+                cb: Rc::new(move |_| {
+                    log.borrow_mut().push(name);
+                    response
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn listeners_are_called_by_priority_and_mask() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut listeners = Vec::new();
+        let continues = EventResponse::Continue;
+
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "first",
+            EventMask::all(),
+            0,
+            continues,
+        );
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "hover",
+            EventMask::HOVER,
+            0,
+            continues,
+        );
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "capture",
+            EventMask::CONTACT,
+            10,
+            continues,
+        );
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "last",
+            EventMask::all(),
+            -5,
+            continues,
+        );
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "second",
+            EventMask::CONTACT | EventMask::MOVE,
+            0,
+            EventResponse::Handled,
+        );
+
+        let response = call_listeners(&listeners, WinTabEvent::StylusActive, |_| true);
+
+        assert_eq!(response, EventResponse::Handled);
+        assert_eq!(*log.borrow(), ["capture", "first", "second", "last"]);
+
+        log.borrow_mut().clear();
+
+        let response = call_listeners(&listeners, WinTabEvent::MaxPressure, |_| true);
+
+        assert_eq!(response, EventResponse::Continue);
+        assert_eq!(*log.borrow(), ["first", "last"]);
+    }
+
+    #[test]
+    fn stopped_events_skip_the_remaining_listeners() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut listeners = Vec::new();
+
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "other",
+            EventMask::all(),
+            0,
+            EventResponse::Continue,
+        );
+        add_logging_listener(
+            &mut listeners,
+            &log,
+            "capture",
+            EventMask::CONTACT,
+            100,
+            EventResponse::Stop,
+        );
+
+        let response = call_listeners(&listeners, WinTabEvent::StylusActive, |_| true);
+
+        assert_eq!(response, EventResponse::Stop);
+        assert_eq!(*log.borrow(), ["capture"]);
+
+        // unregistered listeners aren't called
+        log.borrow_mut().clear();
+
+        let capture = listeners[0].id;
+        let response = call_listeners(&listeners, WinTabEvent::StylusActive, |id| id != capture);
+
+        assert_eq!(response, EventResponse::Continue);
+        assert_eq!(*log.borrow(), ["other"]);
+    }
+
+    #[cfg(feature = "history")]
+    fn history(start: Instant, points: &[(u64, i32, i32)]) -> VecDeque<(Instant, StylusSample)> {
+        points
+            .iter()
+            .map(|&(ms, x, y)| {
+                (
+                    start + Duration::from_millis(ms),
+                    StylusSample {
+                        x,
+                        y,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn positions_are_interpolated_between_samples() {
+        let start = Instant::now();
+        let history = history(start, &[(0, 0, 0), (10, 100, 50), (20, 100, 150)]);
+        let at = |ms| position_at(&history, start + Duration::from_millis(ms));
+
+        assert_eq!(at(0), Some((0.0, 0.0)));
+        assert_eq!(at(5), Some((50.0, 25.0)));
+        assert_eq!(at(10), Some((100.0, 50.0)));
+        assert_eq!(at(15), Some((100.0, 100.0)));
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn positions_are_extrapolated_a_little_past_the_samples() {
+        let start = Instant::now() + Duration::from_secs(1);
+        let history = history(start, &[(0, 0, 0), (10, 100, 50)]);
+        let at = |t| position_at(&history, t);
+
+        assert_eq!(at(start + Duration::from_millis(20)), Some((200.0, 100.0)));
+        assert_eq!(at(start - Duration::from_millis(10)), Some((-100.0, -50.0)));
+
+        // too far from the samples to guess
+        assert_eq!(at(start + Duration::from_millis(61)), None);
+        assert_eq!(at(start - Duration::from_millis(51)), None);
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn positions_need_samples() {
+        let start = Instant::now();
+
+        assert_eq!(position_at(&VecDeque::new(), start), None);
+
+        // a single sample, or samples sharing a timestamp, can't be extrapolated from
+        let single = history(start, &[(0, 4, 2)]);
+        let batch = history(start, &[(0, 0, 0), (0, 4, 2)]);
+
+        assert_eq!(
+            position_at(&single, start + Duration::from_millis(10)),
+            Some((4.0, 2.0))
+        );
+        assert_eq!(
+            position_at(&batch, start + Duration::from_millis(10)),
+            Some((4.0, 2.0))
+        );
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn next_position_carries_on_at_the_same_velocity() {
+        let start = Instant::now();
+
+        assert_eq!(
+            predicted_next(&history(start, &[(0, 0, 0), (10, 50, 50), (20, 60, 40)])),
+            Some((70.0, 30.0))
+        );
+        assert_eq!(
+            predicted_next(&history(start, &[(0, 5, 5), (10, 5, 5)])),
+            Some((5.0, 5.0))
+        );
+        assert_eq!(predicted_next(&history(start, &[(0, 5, 5)])), None);
+        assert_eq!(predicted_next(&VecDeque::new()), None);
+    }
+
+    #[test]
+    fn chattering_contacts_are_debounced() {
+        let debounce = Some(Duration::from_millis(20));
+        let lifted = Instant::now();
+        let after = |ms| lifted + Duration::from_millis(ms);
+
+        assert!(within_debounce(debounce, lifted, after(0)));
+        assert!(within_debounce(debounce, lifted, after(20)));
+        assert!(!within_debounce(debounce, lifted, after(21)));
+
+        // without a debounce every lift is reported
+        assert!(!within_debounce(None, lifted, after(0)));
+    }
+
+    #[test]
+    fn max_pressure_fires_once_per_stroke() {
+        let reached = Cell::new(false);
+        let stroke = |pressures: &[f32]| {
+            // cleared when the contact goes down, as `handle_down` does
+            reached.set(false);
+
+            pressures
+                .iter()
+                .filter(|&&pressure| bottomed_out(&reached, pressure))
+                .count()
+        };
+
+        assert_eq!(stroke(&[0.2, 0.5, 0.98]), 0);
+        assert_eq!(stroke(&[0.5, 0.99, 1.0, 1.0, 0.6, 1.0]), 1);
+        // the next stroke fires again
+        assert_eq!(stroke(&[1.0]), 1);
+    }
+
+    #[cfg(feature = "filters")]
+    #[test]
+    fn event_rate_is_capped() {
+        let start = Instant::now();
+        let mut last = None;
+
+        // a second of samples from a 1000Hz pen
+        let passed = (0..1000)
+            .map(|ms| start + Duration::from_millis(ms))
+            .filter(|&now| {
+                let pass = under_rate_cap(100, last, now);
+
+                if pass {
+                    last = Some(now);
+                }
+
+                pass
+            })
+            .count();
+
+        assert_eq!(passed, 100);
+        assert!(under_rate_cap(100, None, start));
+        assert!(!under_rate_cap(
+            100,
+            Some(start),
+            start + Duration::from_millis(9)
+        ));
+        assert!(under_rate_cap(
+            100,
+            Some(start),
+            start + Duration::from_millis(10)
+        ));
+    }
+
+    #[test]
+    fn relative_moves_accumulate_from_the_last_sample() {
+        let mut origin = None;
+        let mut deltas = Vec::new();
+
+        for position in [
+            (1, 10, 10),
+            (1, 15, 8),
+            (1, 15, 8),
+            (1, 20, 20),
+            (2, 0, 0),
+            (2, -5, 3),
+        ] {
+            deltas.extend(relative_delta(origin, position));
+            origin = Some(position);
+        }
+
+        // nothing for the first sample, a cursor that didn't move, or a change of cursor
+        assert_eq!(deltas, [(5, -2), (5, 12), (-5, 3)]);
+        assert_eq!(
+            deltas
+                .iter()
+                .fold((0, 0), |(x, y), (dx, dy)| (x + dx, y + dy)),
+            (5, 13)
+        );
+    }
+
+    #[test]
+    fn relative_moves_restart_when_the_stylus_comes_into_range() {
+        // coming into range clears the origin, so the jump to where the stylus came back isn't a delta
+        assert_eq!(relative_delta(None, (1, 500, 500)), None);
+        assert_eq!(
+            relative_delta(Some((1, 10, 10)), (1, 500, 500)),
+            Some((490, 490))
+        );
+    }
+
+    #[cfg(feature = "filters")]
+    #[test]
+    fn pressure_threshold_has_hysteresis() {
+        let mut above = false;
+        let mut crossings = Vec::new();
+
+        for pressure in [0.1, 0.5, 0.51, 0.48, 0.52, 0.46, 0.44, 0.49, 0.5, 0.6, 0.0] {
+            if let Some(crossed) = threshold_crossing(above, pressure, 0.5, 0.05) {
+                above = crossed;
+                crossings.push((crossed, pressure));
+            }
+        }
+
+        // chatter around the threshold doesn't cross it again until it's past the hysteresis
+        assert_eq!(
+            crossings,
+            [(true, 0.51), (false, 0.44), (true, 0.6), (false, 0.0)]
+        );
+    }
+
+    #[cfg(feature = "filters")]
+    #[test]
+    fn pressure_under_the_deadzone_is_dropped() {
+        assert_eq!(apply_deadzone(0.049, 0.05), 0.0);
+        assert_eq!(apply_deadzone(0.05, 0.05), 0.05);
+        assert_eq!(apply_deadzone(0.051, 0.05), 0.051);
+
+        // the default deadzone keeps everything
+        assert_eq!(apply_deadzone(0.001, 0.0), 0.001);
+    }
+
+    #[test]
+    fn basic_tablets_support_fewer_events() {
+        let opts = EasyTabOptions::default();
+
+        let basic = supported_events(Capabilities::default(), &opts);
+
+        assert!(basic.contains(EventMask::CONTACT | EventMask::MOVE | EventMask::GESTURES));
+        assert!(!basic.intersects(EventMask::PRESSURE | EventMask::BUTTONS | EventMask::RELATIVE));
+
+        let full = supported_events(
+            Capabilities {
+                pressure: true,
+                tilt: true,
+                buttons: 2,
+                ..Default::default()
+            },
+            &opts,
+        );
+
+        assert_eq!(full, basic | EventMask::PRESSURE | EventMask::BUTTONS);
+    }
+
+    #[test]
+    fn options_add_supported_events() {
+        let opts = EasyTabOptions {
+            synth_pressure: true,
+            relative_mode: true,
+            ..Default::default()
+        };
+
+        assert!(supported_events(Capabilities::default(), &opts)
+            .contains(EventMask::PRESSURE | EventMask::RELATIVE));
+    }
+
+    #[test]
+    fn async_plugin_goes_ahead_of_the_others() {
+        struct Plugins(RefCell<Vec<&'static str>>);
+
+        impl AsyncPlugins<&'static str> for Plugins {
+            fn insert_async_plugin(&self, index: u32, plugin: &&'static str) -> Result<()> {
+                self.0.borrow_mut().insert(index as usize, plugin);
+
+                Ok(())
+            }
+        }
+
+        // e.g. a UI framework sharing the stylus
+        let plugins = Plugins(RefCell::new(vec!["ink", "renderer"]));
+
+        add_async_plugin(&plugins, &"easytab").unwrap();
+        assert_eq!(*plugins.0.borrow(), ["easytab", "ink", "renderer"]);
+    }
+
+    // a tablet that isn't bound to a window, so its listeners can be driven directly
+    fn disconnected_tablet(opts: EasyTabOptions) -> EasyTablet {
+        // already being initialised on this thread is fine
+        let _ = unsafe { CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED) };
+
+        EasyTablet::disconnected_options(opts).unwrap()
+    }
+
+    #[test]
+    fn chained_listeners_are_registered() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (first, second) = (log.clone(), log.clone());
+
+        let tablet = disconnected_tablet(EasyTabOptions::default())
+            .with_listener(Box::new(move |_| first.borrow_mut().push("first")))
+            .with_listener(Box::new(move |_| second.borrow_mut().push("second")));
+
+        tablet.handle_event(WinTabEvent::Ready).unwrap();
+
+        assert_eq!(*log.borrow(), ["first", "second"]);
+    }
+
+    #[test]
+    fn listeners_can_only_be_removed_once() {
+        let tablet = disconnected_tablet(EasyTabOptions::default());
+        let id = tablet.add_listener(Box::new(|_| {}));
+
+        assert!(tablet.remove_listener(id));
+        assert!(!tablet.remove_listener(id));
+    }
+
+    #[cfg(feature = "dispatch-thread")]
+    #[test]
+    fn threaded_listeners_can_only_be_removed_once() {
+        let tablet = disconnected_tablet(EasyTabOptions {
+            dispatch_thread: true,
+            ..Default::default()
+        });
+        let id = tablet.add_threaded_listener(Box::new(|_| {}));
+        let local = tablet.add_listener(Box::new(|_| {}));
+
+        assert!(tablet.remove_listener(id));
+        assert!(!tablet.remove_listener(id));
+        assert!(tablet.remove_listener(local));
+        assert!(!tablet.remove_listener(local));
+
+        // an id that was never handed out
+        assert!(!tablet.remove_listener(ListenerId(1000)));
+    }
+
+    #[test]
+    fn handedness_is_kept_from_the_options() {
+        assert_eq!(
+            disconnected_tablet(EasyTabOptions::default()).handedness(),
+            Handedness::Right
+        );
+        assert_eq!(
+            disconnected_tablet(EasyTabOptions {
+                handedness: Handedness::Left,
+                ..Default::default()
+            })
+            .handedness(),
+            Handedness::Left
+        );
+    }
+
+    #[test]
+    fn retries_back_off_up_to_the_maximum() {
+        let retry = RetryOptions {
+            max_retries: 5,
+            initial_delay: Duration::from_millis(25),
+            max_delay: Duration::from_millis(150),
+            backoff_factor: 2,
+        };
+
+        let delays: Vec<_> = (0..)
+            .map_while(|retries| retry_delay(&retry, retries))
+            .map(|delay| delay.as_millis())
+            .collect();
+
+        assert_eq!(delays, [25, 50, 100, 150, 150]);
+    }
+
+    #[test]
+    fn retries_can_be_turned_off() {
+        let retry = RetryOptions {
+            max_retries: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(retry_delay(&retry, 0), None);
+        assert_eq!(
+            retry_delay(&RetryOptions::default(), 0),
+            Some(RetryOptions::default().initial_delay)
+        );
+    }
+
+    #[test]
+    fn contacts_are_tracked_until_they_lift() {
+        let tablet = disconnected_tablet(EasyTabOptions::default());
+        let contact = |id, kind, x| Contact {
+            id,
+            kind,
+            x,
+            y: 0,
+            pressure: 0.5,
+            size: None,
+        };
+
+        assert!(tablet.active_contacts().is_empty());
+
+        // two fingers down at once, the way `handle_down` records them
+        for c in [
+            contact(1, ContactKind::Touch, 10),
+            contact(2, ContactKind::Touch, 20),
+        ] {
+            tablet.contacts.borrow_mut().insert(c.id, c);
+        }
+        tablet.primary_contact.set(Some(1));
+
+        let mut contacts = tablet.active_contacts();
+        contacts.sort_by_key(|c| c.id);
+
+        assert_eq!(
+            contacts,
+            [
+                contact(1, ContactKind::Touch, 10),
+                contact(2, ContactKind::Touch, 20)
+            ]
+        );
+
+        tablet.lift_contact(1).unwrap();
+
+        assert_eq!(
+            tablet.active_contacts(),
+            [contact(2, ContactKind::Touch, 20)]
+        );
+        assert_eq!(tablet.primary_contact_id(), None);
+
+        tablet.lift_contact(2).unwrap();
+
+        assert!(tablet.active_contacts().is_empty());
+    }
+}
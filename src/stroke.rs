@@ -0,0 +1,340 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+#[cfg(feature = "backend-rts")]
+use std::time::Instant;
+
+use crate::win32::WinTabEvent;
+use crate::{ListenerId, Rect, StylusSample, TabletBackend};
+
+/// The samples collected while the stylus was touching the digitiser.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stroke {
+    /// The samples in the order they arrived, from the stylus touching down to it lifting.
+    pub points: Vec<StylusSample>,
+}
+
+impl Stroke {
+    /// Returns the stroke as the `d` attribute of an SVG `<path>`, a line through every point, e.g.
+    /// `"M 10 20 L 12 24 L 15 27"`. Empty if the stroke has no points.
+    ///
+    /// The points are in the same units as the samples. Use [`to_svg_outline`](Self::to_svg_outline) for a path that
+    /// follows the pressure.
+    pub fn to_svg_path(&self) -> String {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{} {} {}", if i == 0 { "M" } else { "L" }, p.x, p.y))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the stroke as the `d` attribute of a filled SVG `<path>`, outlining a line that is `max_width` wide at
+    /// full pressure and narrows as the pressure drops. Empty if the stroke has no points.
+    pub fn to_svg_outline(&self, max_width: f32) -> String {
+        let points: Vec<(f32, f32, f32)> = self
+            .points
+            .iter()
+            .map(|p| (p.x as f32, p.y as f32, p.pressure))
+            .collect();
+
+        let last = match points.len().checked_sub(1) {
+            Some(last) => last,
+            None => return String::new(),
+        };
+
+        // each point offset either side of the line, along the normal of the direction through it
+        let (left, right): (Vec<_>, Vec<_>) = (0..=last)
+            .map(|i| {
+                let (ax, ay, _) = points[i.saturating_sub(1)];
+                let (bx, by, _) = points[(i + 1).min(last)];
+                let (x, y, pressure) = points[i];
+
+                let (dx, dy) = (bx - ax, by - ay);
+                let len = dx.hypot(dy);
+                let half = pressure * max_width / 2.0;
+
+                let (nx, ny) = if len > 0.0 {
+                    (-dy / len * half, dx / len * half)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                ((x + nx, y + ny), (x - nx, y - ny))
+            })
+            .unzip();
+
+        let outline: Vec<String> = left
+            .iter()
+            .chain(right.iter().rev())
+            .enumerate()
+            .map(|(i, (x, y))| format!("{} {:.1} {:.1}", if i == 0 { "M" } else { "L" }, x, y))
+            .collect();
+
+        outline.join(" ") + " Z"
+    }
+}
+
+/// Collects the samples between the stylus touching and leaving the digitiser into [`Stroke`]s.
+///
+/// ```
+/// let recorder = StrokeRecorder::new(&tablet, Box::new(|stroke| canvas.draw(&stroke.points)));
+/// //...
+/// recorder.detach(&tablet);
+/// ```
+pub struct StrokeRecorder {
+    id: ListenerId,
+}
+
+impl StrokeRecorder {
+    /// Starts recording strokes from the tablet, calling `on_stroke` with each one once the stylus is lifted.
+    pub fn new(tablet: &impl TabletBackend, on_stroke: Box<dyn Fn(Stroke)>) -> Self {
+        // `None` while the stylus isn't touching the digitiser
+        let current: Rc<RefCell<Option<Stroke>>> = Rc::default();
+
+        let id = tablet.add_listener(Box::new(move |event| match event {
+            WinTabEvent::StylusActive => *current.borrow_mut() = Some(Stroke::default()),
+
+            WinTabEvent::StylusMove(sample) => {
+                if let Some(stroke) = current.borrow_mut().as_mut() {
+                    stroke.points.push(sample);
+                }
+            }
+
+            WinTabEvent::StylusInactive => {
+                let stroke = current.borrow_mut().take();
+
+                if let Some(stroke) = stroke.filter(|s| !s.points.is_empty()) {
+                    on_stroke(stroke);
+                }
+            }
+
+            _ => {}
+        }));
+
+        Self { id }
+    }
+
+    /// Stops recording strokes. A stroke in progress is discarded.
+    pub fn detach(self, tablet: &impl TabletBackend) {
+        tablet.remove_listener(self.id);
+    }
+}
+
+/// Statistics about a finished stroke. Refer to [`EasyTablet::last_stroke_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StrokeStats {
+    /// The number of samples in the stroke.
+    pub sample_count: usize,
+    /// How long the stylus was touching the digitiser.
+    pub duration: Duration,
+    /// The distance the stylus travelled, in the same units as the samples.
+    pub length: f32,
+    /// The mean pressure over the samples.
+    pub avg_pressure: f32,
+    /// The highest pressure in the stroke.
+    pub max_pressure: f32,
+    /// The smallest rect containing every sample.
+    pub bounding_box: Rect,
+}
+
+// works out the statistics of a stroke as its samples arrive, so the samples don't have to be kept
+#[cfg(feature = "backend-rts")]
+pub(crate) struct StatsBuilder {
+    start: Instant,
+    stats: StrokeStats,
+    pressure_sum: f32,
+    last: Option<(i32, i32)>,
+}
+
+#[cfg(feature = "backend-rts")]
+impl StatsBuilder {
+    pub(crate) fn new(start: Instant) -> Self {
+        Self {
+            start,
+            stats: StrokeStats::default(),
+            pressure_sum: 0.0,
+            last: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, sample: &StylusSample) {
+        let stats = &mut self.stats;
+
+        match self.last.replace((sample.x, sample.y)) {
+            Some((x, y)) => {
+                stats.length += ((sample.x - x) as f32).hypot((sample.y - y) as f32);
+
+                let bbox = &mut stats.bounding_box;
+                bbox.left = bbox.left.min(sample.x);
+                bbox.top = bbox.top.min(sample.y);
+                bbox.right = bbox.right.max(sample.x);
+                bbox.bottom = bbox.bottom.max(sample.y);
+            }
+            None => {
+                stats.bounding_box = Rect {
+                    left: sample.x,
+                    top: sample.y,
+                    right: sample.x,
+                    bottom: sample.y,
+                }
+            }
+        }
+
+        stats.sample_count += 1;
+        stats.max_pressure = stats.max_pressure.max(sample.pressure);
+        self.pressure_sum += sample.pressure;
+    }
+
+    // the statistics of the stroke, or `None` if it had no samples
+    pub(crate) fn finish(&self, end: Instant) -> Option<StrokeStats> {
+        let count = self.stats.sample_count;
+
+        (count > 0).then(|| StrokeStats {
+            duration: end.duration_since(self.start),
+            avg_pressure: self.pressure_sum / count as f32,
+            ..self.stats
+        })
+    }
+}
+
+/// A line between two consecutive samples in a stroke.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Segment {
+    /// The earlier of the two samples, where the line starts.
+    pub from: StylusSample,
+    /// The later of the two samples, where the line ends.
+    pub to: StylusSample,
+}
+
+/// Turns a stream of events into the segments between consecutive samples within each stroke, ready to be drawn as
+/// lines.
+///
+/// Strokes are broken up at [`WinTabEvent::StylusActive`] and [`WinTabEvent::StylusInactive`], so no segment joins
+/// two strokes. Events other than [`WinTabEvent::StylusMove`] are otherwise ignored.
+///
+/// ```
+/// for segment in segments(events) {
+///     canvas.line(segment.from, segment.to);
+/// }
+/// ```
+pub fn segments(events: impl Iterator<Item = WinTabEvent>) -> impl Iterator<Item = Segment> {
+    // the previous sample in the current stroke
+    let mut previous: Option<StylusSample> = None;
+
+    events.filter_map(move |event| match event {
+        WinTabEvent::StylusActive | WinTabEvent::StylusInactive => {
+            previous = None;
+            None
+        }
+
+        WinTabEvent::StylusMove(sample) => previous
+            .replace(sample)
+            .map(|from| Segment { from, to: sample }),
+
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(x: i32, y: i32, pressure: f32) -> StylusSample {
+        StylusSample {
+            x,
+            y,
+            pressure,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn segments_join_consecutive_samples_within_a_stroke() {
+        let (a, b, c, d) = (
+            sample(0, 0, 1.0),
+            sample(1, 0, 1.0),
+            sample(2, 0, 1.0),
+            sample(5, 5, 1.0),
+        );
+
+        let events = vec![
+            WinTabEvent::StylusActive,
+            WinTabEvent::StylusMove(a),
+            WinTabEvent::StylusMove(b),
+            WinTabEvent::HoverMove(d),
+            WinTabEvent::StylusMove(c),
+            WinTabEvent::StylusInactive,
+            WinTabEvent::StylusActive,
+            WinTabEvent::StylusMove(d),
+            WinTabEvent::StylusInactive,
+        ];
+
+        let segments: Vec<Segment> = segments(events.into_iter()).collect();
+
+        assert_eq!(
+            segments,
+            [Segment { from: a, to: b }, Segment { from: b, to: c }]
+        );
+    }
+
+    #[test]
+    fn svg_path_draws_a_line_through_every_point() {
+        let stroke = Stroke {
+            points: vec![
+                sample(10, 20, 1.0),
+                sample(12, 24, 1.0),
+                sample(15, 27, 1.0),
+            ],
+        };
+
+        assert_eq!(stroke.to_svg_path(), "M 10 20 L 12 24 L 15 27");
+        assert_eq!(Stroke::default().to_svg_path(), "");
+    }
+
+    #[test]
+    fn svg_outline_follows_the_pressure() {
+        let stroke = Stroke {
+            points: vec![sample(0, 0, 1.0), sample(10, 0, 0.5)],
+        };
+
+        // the line runs along x, so the outline is offset along y by half the width at each point
+        assert_eq!(
+            stroke.to_svg_outline(4.0),
+            "M 0.0 2.0 L 10.0 1.0 L 10.0 -1.0 L 0.0 -2.0 Z"
+        );
+        assert_eq!(Stroke::default().to_svg_outline(4.0), "");
+    }
+
+    #[cfg(feature = "backend-rts")]
+    #[test]
+    fn stats_builder_sums_up_the_samples() {
+        let start = Instant::now();
+        let mut builder = StatsBuilder::new(start);
+
+        assert_eq!(builder.finish(start), None);
+
+        builder.push(&sample(0, 0, 0.2));
+        builder.push(&sample(3, 4, 0.6));
+        builder.push(&sample(3, -2, 0.4));
+
+        let end = start + Duration::from_millis(50);
+
+        assert_eq!(
+            builder.finish(end),
+            Some(StrokeStats {
+                sample_count: 3,
+                duration: Duration::from_millis(50),
+                length: 11.0,
+                avg_pressure: (0.2 + 0.6 + 0.4) / 3.0,
+                max_pressure: 0.6,
+                bounding_box: Rect {
+                    left: 0,
+                    top: -2,
+                    right: 3,
+                    bottom: 4,
+                },
+            })
+        );
+    }
+}
@@ -1,17 +1,12 @@
-use std::cell::Cell;
-use std::rc::Rc;
+use std::time::Instant;
 
-use windows::core::{implement, Error, IUnknown, InParam, Result, GUID, HRESULT};
-use windows::Win32::Foundation::{HANDLE_PTR, POINT};
+use raw_window_handle::RawWindowHandle;
+#[cfg(any(feature = "backend-rts", feature = "backend-hid"))]
+use windows::core::Error;
+use windows::core::HRESULT;
+use windows::Win32::Foundation::{HANDLE_PTR, RECT};
 
-use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
-use windows::Win32::UI::TabletPC::{
-    IInkTablet, IRealTimeStylus, IStylusAsyncPlugin, IStylusAsyncPlugin_Impl, IStylusPlugin,
-    IStylusPlugin_Impl, RTSDI_AllData, RealTimeStylus, RealTimeStylusDataInterest, StylusInfo,
-    SYSTEM_EVENT_DATA,
-};
-
-use crate::{EasyTabError, EasyTabOptions, EasyTabResult, EasyTablet, __InnerTablet};
+use crate::{EasyTabError, EasyTabResult, Rect, TabletBounds, Warning};
 
 // ///
 // #[derive(Default, Clone, Copy, Debug)]
@@ -54,7 +49,8 @@ use crate::{EasyTabError, EasyTabOptions, EasyTabResult, EasyTablet, __InnerTabl
 // pub struct WinTab {}
 
 //
-#[derive(Default)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WinTabletIndex {
     #[default]
     Default,
@@ -93,349 +89,546 @@ pub enum WinTabletIndex {
 // }
 
 // function used to map a windows errors to an easytab error
-const ERROR_FN: fn(Error) -> EasyTabError = |e| EasyTabError::WinError(e.message());
-
-impl EasyTablet {
-    /// Initialises a tablet.
-    ///
-    /// ## Arguments
-    ///
-    /// - `hwnd`: `HANDLE_PTR` - a handle to a window to bind the tablet to.
-    ///
-    /// <br>
-    ///
-    /// **Note**: This functions assumes that [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex) has previously been called.
-    /// - Refer to [`init_options`] for more info.
-
-    // pub fn init(hwnd: HANDLE_PTR) -> EasyTabResult<Self> {
-    //     EasyTablet::init_options(hwnd, EasyTabOptions::default())
-    // }
+#[cfg(any(feature = "backend-rts", feature = "backend-hid"))]
+pub(crate) const ERROR_FN: fn(Error) -> EasyTabError = |e| EasyTabError::WinError(e.message());
+
+bitflags::bitflags! {
+    /// The status bits of a packet, the per-packet record of the contact, eraser and barrel button state.
+    #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PacketStatus: i32 {
+        /// The tip of the stylus is touching the digitiser.
+        const TIP = 0x1;
+        /// The stylus is inverted, i.e. the eraser end is in use.
+        const INVERTED = 0x2;
+        /// The barrel button is pressed.
+        const BARREL = 0x8;
+    }
+}
 
-    pub fn init<W: Into<usize>>(hwnd: W) -> EasyTabResult<Self> {
-        EasyTablet::init_options(HANDLE_PTR(hwnd.into()), EasyTabOptions::default())
+bitflags::bitflags! {
+    /// The kinds of events a listener is interested in. Refer to [`EasyTablet::add_filtered_listener`].
+    pub struct EventMask: u32 {
+        /// [`WinTabEvent::StylusActive`] and [`WinTabEvent::StylusInactive`].
+        const CONTACT = 0x1;
+        /// [`WinTabEvent::StylusMove`].
+        const MOVE = 0x2;
+        /// [`WinTabEvent::HoverMove`].
+        const HOVER = 0x4;
+        /// [`WinTabEvent::StylusButtonDown`], [`WinTabEvent::StylusButtonUp`] and [`WinTabEvent::ButtonChanged`].
+        const BUTTONS = 0x8;
+        /// [`WinTabEvent::PressureChanged`], [`WinTabEvent::PressureAbove`], [`WinTabEvent::PressureBelow`] and
+        /// [`WinTabEvent::MaxPressure`].
+        const PRESSURE = 0x10;
+        /// [`WinTabEvent::RelativeMove`].
+        const RELATIVE = 0x20;
+        /// [`WinTabEvent::DoubleTap`], [`WinTabEvent::AirTap`] and [`WinTabEvent::SystemGesture`].
+        const GESTURES = 0x40;
+        /// [`WinTabEvent::Ready`], [`WinTabEvent::TabletChanged`], [`WinTabEvent::MappingChanged`],
+        /// [`WinTabEvent::Warning`] and [`WinTabEvent::Error`].
+        const STATUS = 0x80;
+        /// [`WinTabEvent::EnterWindow`] and [`WinTabEvent::LeaveWindow`].
+        const WINDOW = 0x100;
     }
+}
 
-    /// Initialises a tablet with the given options.
+/// A single decoded packet from the tablet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StylusSample {
+    /// The x position of the stylus, in the coordinate space set with
+    /// [`EasyTabOptions::emit_coordinate_space`] (tablet coordinates by default).
+    pub x: i32,
+    /// The y position of the stylus, in the coordinate space set with
+    /// [`EasyTabOptions::emit_coordinate_space`] (tablet coordinates by default).
+    pub y: i32,
+    /// The height of the stylus above the digitiser, if the tablet reports it.
+    pub z: Option<i32>,
+    /// The pressure of the stylus, normalised to `0.0..=1.0`.
+    pub pressure: f32,
+    /// The tilt of the stylus along the x and y axes in degrees, if the tablet reports it.
+    pub tilt: Option<(f32, f32)>,
+    /// The direction the stylus leans in, in degrees. Refer to [`tilt_to_spherical`](crate::tilt_to_spherical).
     ///
-    /// ## Arguments
+    /// Read from the tablet if it reports it, otherwise derived from [`tilt`](Self::tilt).
+    pub azimuth: Option<f32>,
+    /// The angle between the stylus and the surface, in degrees. Refer to [`tilt_to_spherical`](crate::tilt_to_spherical).
     ///
-    /// - `hwnd`: `HANDLE_PTR`&emsp;&emsp;- a handle to a window to bind the tablet to.
-    /// - `opts`: `WinTabOptions` - the initialisation options for the tablet.
+    /// Read from the tablet if it reports it, otherwise derived from [`tilt`](Self::tilt).
+    pub altitude: Option<f32>,
+    /// The rotation of the stylus around its side-to-side axis in degrees, if the pen reports it (usually only 3D-capable
+    /// pens do).
+    pub pitch: Option<f32>,
+    /// The rotation of the stylus around its lengthwise axis in degrees, if the pen reports it.
+    pub roll: Option<f32>,
+    /// The clockwise rotation of the stylus around its lengthwise axis in degrees, if the pen reports it (usually only
+    /// art pens do).
+    pub twist: Option<f32>,
+    /// The rotation of the stylus around the axis perpendicular to the digitiser in degrees, if the pen reports it.
+    pub yaw: Option<f32>,
+    /// The serial number of the pen, if the tablet reports it. Distinguishes individual physical pens.
+    pub serial: Option<i32>,
+    /// The status bits of the packet, if the tablet reports them.
+    pub status: Option<PacketStatus>,
+    /// The width and height of the contact, if the digitiser reports them (usually only touch digitisers do).
+    pub contact_size: Option<(i32, i32)>,
+    /// The id of the cursor (pen tip, eraser, puck...) the sample is from. Refer to
+    /// [`EasyTablet::state_for_cursor`].
     ///
-    /// <br>
+    /// Backends that can't tell cursors apart always report `0`.
+    pub cursor: u32,
+    /// Whether the sample is from a pen, a finger or a mouse, so a listener can tell them apart without looking up the
+    /// [cursor](Self::cursor).
     ///
-    /// **Note**: This functions assumes that [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex) has previously been called.
+    /// Backends that only read pens always report [`InputSource::Pen`].
+    pub input_source: InputSource,
+    /// When the sample was received. Not serialised, as an `Instant` is only meaningful within the process.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub instant: Option<Instant>,
+    /// When the sample was received, as the Windows message time (in milliseconds since the system started, as
+    /// returned by `GetMessageTime`). The same clock as the timestamps on keyboard and mouse messages, so pen input can
+    /// be lined up with them.
     ///
-    /// ```
-    /// // before calling `init_options`
-    /// unsafe {
-    ///     CoInitializeEx(
-    ///         std::ptr::null(),
-    ///         COINIT_APARTMENTTHREADED,
-    ///     )
-    ///     .expect("failed to initalise COM");
-    /// }
-    /// //...
-    /// let tablet = EasyTablet::init_options(...)?;
-    /// ```
-    pub fn init_options(hwnd: HANDLE_PTR, opts: EasyTabOptions) -> EasyTabResult<Self> {
-        // create a real time stylus
-        let stylus: IRealTimeStylus = unsafe {
-            CoCreateInstance(&RealTimeStylus, InParam::null(), CLSCTX_INPROC_SERVER)
-                .map_err(ERROR_FN)?
-        };
-
-        // bind the stylus to the current window
-        unsafe { stylus.SetHWND(hwnd).map_err(ERROR_FN)? };
-
-        let slf = Self(Rc::new(__InnerTablet {
-            stylus,
-            opts,
-
-            on: Cell::default(),
-
-            active: Cell::default(),
-            x: Cell::default(),
-            y: Cell::default(),
-            pressure: Cell::default(),
-        }));
-
-        // pass a reference of ourselves into the handler so it can call the `handle_event` fn
-        let ash: IStylusAsyncPlugin = AsyncStylusHandler(Rc::clone(&slf.0)).into();
-
-        // add the handler to the stylus
-        unsafe {
-            slf.stylus
-                .AddStylusAsyncPlugin(
-                    slf.stylus.GetStylusAsyncPluginCount().map_err(ERROR_FN)?,
-                    &ash,
-                )
-                .map_err(ERROR_FN)?
-        };
+    /// `None` for backends that don't receive packets through the message queue.
+    pub os_time: Option<u32>,
+}
 
-        Ok(slf)
+impl StylusSample {
+    /// Returns the sample with its position normalised to `[0, 1]` within the given bounds (e.g. from
+    /// [`EasyTablet::bounds`]). Refer to [`TabletBounds::normalize`].
+    #[must_use]
+    pub fn to_normalized(&self, bounds: &TabletBounds) -> NormalizedSample {
+        let (x, y) = bounds.normalize(self.x, self.y);
+
+        NormalizedSample {
+            x,
+            y,
+            sample: *self,
+        }
     }
+}
 
-    /// Enables the tablet.
-    pub fn enable(&self) -> EasyTabResult<()> {
-        unsafe { self.stylus.SetEnabled(true).map_err(ERROR_FN)? };
+/// The kind of tip on the stylus, as far as the driver reports it. Refer to [`EasyTablet::tip_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TipType {
+    /// A regular pen tip.
+    Pen,
+    /// The eraser end of a pen, or a dedicated eraser.
+    Eraser,
+    /// A brush tip.
+    Brush,
+    /// A marker, e.g. a chisel tip that reports its rotation.
+    Marker,
+    /// An airbrush.
+    Airbrush,
+    /// The driver doesn't say, or reports something not listed here.
+    Unknown,
+}
 
-        Ok(())
+#[cfg(feature = "backend-rts")]
+impl TipType {
+    // drivers only describe the tip through the cursor's name (e.g. "Art Marker" or "Airbrush"), so it is matched on
+    pub(crate) fn from_cursor(name: &str, inverted: bool) -> Self {
+        let name = name.to_lowercase();
+
+        if inverted || name.contains("eraser") {
+            TipType::Eraser
+        } else if name.contains("airbrush") {
+            TipType::Airbrush
+        } else if name.contains("marker") {
+            TipType::Marker
+        } else if name.contains("brush") {
+            TipType::Brush
+        } else if name.contains("pen") || name.contains("stylus") {
+            TipType::Pen
+        } else {
+            TipType::Unknown
+        }
     }
+}
 
-    /// Disables the tablet.
-    pub fn disable(&self) -> EasyTabResult<()> {
-        unsafe { self.stylus.SetEnabled(false).map_err(ERROR_FN)? };
+/// The state kept for each cursor that has interacted with the tablet. Refer to [`EasyTablet::state_for_cursor`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorState {
+    /// The id of the cursor.
+    pub id: u32,
+    /// What the cursor is.
+    pub kind: ContactKind,
+    /// Whether the cursor is touching the digitiser.
+    pub down: bool,
+    /// The most recent sample from the cursor, or `None` if it hasn't sent any packets yet.
+    pub last_sample: Option<StylusSample>,
+}
 
-        Ok(())
-    }
+/// A [`StylusSample`] with its position normalised to `[0, 1]`. Refer to [`StylusSample::to_normalized`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NormalizedSample {
+    /// The normalised x position.
+    pub x: f32,
+    /// The normalised y position.
+    pub y: f32,
+    /// The sample this was normalised from, for the rest of the fields.
+    pub sample: StylusSample,
+}
 
-    pub fn on(&self, cb: Box<dyn Fn(WinTabEvent)>) {
-        self.on.set(Some(cb))
-    }
+/// The kind of device a sample came from. Refer to [`StylusSample::input_source`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputSource {
+    /// A pen, either end.
+    #[default]
+    Pen,
+    /// A finger on a touch digitiser.
+    Touch,
+    /// A mouse, or a puck on a digitiser that reports itself as one.
+    Mouse,
+}
 
-    /// Returns whether a finger or stylus is activating the digitiser.
-    pub fn active(&self) -> bool {
-        self.active.get()
+impl From<ContactKind> for InputSource {
+    fn from(kind: ContactKind) -> Self {
+        match kind {
+            ContactKind::Pen | ContactKind::Eraser => InputSource::Pen,
+            ContactKind::Touch => InputSource::Touch,
+            ContactKind::Mouse => InputSource::Mouse,
+        }
     }
+}
 
-    /// Returns the x position where the finger or stylus is making contact with the digitiser.
-    pub fn x(&self) -> i32 {
-        self.x.get()
-    }
+/// What is touching the digitiser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactKind {
+    /// The tip of a pen.
+    Pen,
+    /// The inverted end of a pen.
+    Eraser,
+    /// A finger on a touch digitiser.
+    Touch,
+    /// A mouse, or a puck on a digitiser that reports itself as one.
+    Mouse,
+}
 
-    /// Returns the y position where the finger or stylus is making contact with the digitiser.
-    pub fn y(&self) -> i32 {
-        self.y.get()
-    }
+/// A gesture recognised by Windows rather than the crate. Refer to [`WinTabEvent::SystemGesture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SystemGesture {
+    Tap,
+    DoubleTap,
+    /// A tap with the barrel button held.
+    RightTap,
+    Drag,
+    /// A drag with the barrel button held.
+    RightDrag,
+    /// The stylus was held down long enough for a press and hold (by default, a right click once it lifts).
+    HoldEnter,
+    /// The stylus moved or lifted after a [`HoldEnter`](Self::HoldEnter).
+    HoldLeave,
+    HoverEnter,
+    HoverLeave,
+    /// A quick straight stroke, which Windows maps to actions like scrolling or going back.
+    Flick,
+}
 
-    /// Returns the pressure of the finger or stylus on the digitiser.
-    pub fn pressure(&self) -> f32 {
-        self.pressure.get()
-    }
+/// A contact that is currently touching the digitiser. Refer to [`EasyTablet::active_contacts`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// The id of the cursor making the contact. Stays the same for as long as the contact is down.
+    pub id: u32,
+    /// What is making the contact: the tip or eraser of a pen, a finger or a mouse.
+    pub kind: ContactKind,
+    /// The x position of the contact, in the coordinate space set with
+    /// [`EasyTabOptions::emit_coordinate_space`] (tablet coordinates by default).
+    pub x: i32,
+    /// The y position of the contact, in the coordinate space set with
+    /// [`EasyTabOptions::emit_coordinate_space`] (tablet coordinates by default).
+    pub y: i32,
+    /// The pressure of the contact, normalised to `0.0..=1.0`.
+    pub pressure: f32,
+    /// The width and height of the contact, if the digitiser reports them. Refer to [`StylusSample::contact_size`].
+    pub size: Option<(i32, i32)>,
 }
 
-// TODO: Convert to trait?
-impl __InnerTablet {
-    // handles a stylus event
-    fn handle_event(&self, event: WinTabEvent) -> Result<()> {
-        match event {
-            WinTabEvent::StylusActive => self.active.set(true),
-            WinTabEvent::StylusInactive => self.active.set(false),
-
-            WinTabEvent::StylusButtonDown(x, y) | WinTabEvent::StylusButtonUp(x, y) => {
-                self.x.set(x);
-                self.y.set(y);
-            }
-            _ => todo!(),
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
         }
+    }
+}
 
-        // let on_ptr = self.on.
-
-        // if let Some(on) = on_ptr.into() {
-        //     let evfn = unsafe { on.as_ref().unwrap() };
-
-        // }
-
-        // if let Some(on) = &self.on.get_mut() {
-        //     (*on)(event);
-        // }
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
 
-        Ok(())
+// extracts the window from a raw window handle, which has to be a win32 one
+pub(crate) fn hwnd_from_handle(handle: RawWindowHandle) -> EasyTabResult<HANDLE_PTR> {
+    match handle {
+        RawWindowHandle::Win32(handle) => Ok(HANDLE_PTR(handle.hwnd as usize)),
+        _ => Err(EasyTabError::UnsupportedHandle),
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WinTabEvent {
     StylusActive,
     StylusInactive,
     //...............x....y
     StylusButtonDown(i32, i32),
     StylusButtonUp(i32, i32),
+    StylusMove(StylusSample),
+    HoverMove(StylusSample),
+    Warning(Warning),
+    /// The pressure changed by more than [`EasyTabOptions::pressure_change_threshold`], regardless of whether the
+    /// stylus moved.
+    PressureChanged(f32),
+    /// The pressure rose past [`EasyTabOptions::pressure_threshold`].
+    PressureAbove(f32),
+    /// The pressure fell back under [`EasyTabOptions::pressure_threshold`], less the hysteresis.
+    PressureBelow(f32),
+    /// The pressure reached its maximum (the pen "bottomed out"). Emitted at most once per stroke.
+    MaxPressure,
+    /// A tablet was connected and re-initialised (only with [`EasyTabOptions::retry_on_change`]).
+    TabletChanged,
+    /// Re-initialising a connected tablet failed after every retry, or the stylus reported an error in one of its
+    /// plugins.
+    Error(#[cfg_attr(feature = "serde", serde(with = "hresult"))] HRESULT),
+    /// The tablet was enabled, and everything derived from it ([`capabilities`](EasyTablet::capabilities),
+    /// [`bounds`](EasyTablet::bounds) and the coordinate mapping) was refreshed.
+    Ready,
+    /// The tablet-to-screen mapping changed (e.g. the resolution changed or a monitor was added), and the tablet
+    /// [`bounds`](EasyTablet::bounds) and coordinate mapping were refreshed.
+    MappingChanged,
+    /// A barrel button was pressed or released. `index` is the button's position in
+    /// [`button_guids`](EasyTablet::button_guids).
+    ButtonChanged {
+        index: usize,
+        pressed: bool,
+        x: i32,
+        y: i32,
+    },
+    /// How far the stylus moved since the previous sample (only with [`EasyTabOptions::relative_mode`]).
+    RelativeMove {
+        dx: i32,
+        dy: i32,
+    },
+    /// The stylus tapped twice in quick succession (only with [`EasyTabOptions::double_tap_config`]). The position is
+    /// where the second tap ended.
+    DoubleTap {
+        x: i32,
+        y: i32,
+    },
+    /// A button on the stylus was clicked while it hovered, without touching the digitiser. Refer to
+    /// [`Capabilities::air_tap`].
+    AirTap {
+        x: i32,
+        y: i32,
+    },
+    /// Windows recognised a system gesture, at the given position in tablet coordinates.
+    ///
+    /// Returning [`EventResponse::Handled`](crate::EventResponse::Handled) for
+    /// [`SystemGesture::HoldEnter`] or [`SystemGesture::Flick`] turns off the right click or flick action Windows ties
+    /// to the gesture on the bound window, until the tablet is dropped. Windows may already have acted on the gesture
+    /// being reported.
+    SystemGesture {
+        gesture: SystemGesture,
+        x: i32,
+        y: i32,
+    },
+    /// The stylus moved onto the client area of the bound window. Unlike coming into range of the digitiser, this
+    /// follows where the stylus maps to, e.g. for showing a cursor over a canvas.
+    EnterWindow,
+    /// The stylus moved off the client area of the bound window, or went out of range while over it.
+    LeaveWindow,
 }
 
-// the plugin added to the real time stylus to allow getting real time events from the stylus (asynchronously)
-#[implement(IStylusAsyncPlugin)]
-struct AsyncStylusHandler(Rc<__InnerTablet>);
-
-impl IStylusPlugin_Impl for AsyncStylusHandler {
-    fn RealTimeStylusEnabled(
-        &self,
-        _: &Option<IRealTimeStylus>,
-        _: u32,
-        _: *const u32,
-    ) -> Result<()> {
-        Ok(())
-    }
-
-    fn RealTimeStylusDisabled(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        _: u32,
-        _: *const u32,
-    ) -> Result<()> {
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        self.0.as_ref().handle_event(WinTabEvent::StylusInactive)
-    }
-
-    fn StylusInRange(&self, _: &Option<IRealTimeStylus>, _: u32, _: u32) -> Result<()> {
-        Ok(())
-    }
-
-    fn StylusOutOfRange(&self, pirtssrc: &Option<IRealTimeStylus>, _: u32, _: u32) -> Result<()> {
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        self.0.as_ref().handle_event(WinTabEvent::StylusInactive)
+impl WinTabEvent {
+    /// The kind of event this is, for filtering listeners. Refer to [`EasyTablet::add_filtered_listener`].
+    pub fn mask(&self) -> EventMask {
+        match self {
+            WinTabEvent::StylusActive | WinTabEvent::StylusInactive => EventMask::CONTACT,
+            WinTabEvent::StylusMove(_) => EventMask::MOVE,
+            WinTabEvent::HoverMove(_) => EventMask::HOVER,
+            WinTabEvent::StylusButtonDown(..)
+            | WinTabEvent::StylusButtonUp(..)
+            | WinTabEvent::ButtonChanged { .. } => EventMask::BUTTONS,
+            WinTabEvent::PressureChanged(_)
+            | WinTabEvent::PressureAbove(_)
+            | WinTabEvent::PressureBelow(_)
+            | WinTabEvent::MaxPressure => EventMask::PRESSURE,
+            WinTabEvent::RelativeMove { .. } => EventMask::RELATIVE,
+            WinTabEvent::EnterWindow | WinTabEvent::LeaveWindow => EventMask::WINDOW,
+            WinTabEvent::DoubleTap { .. }
+            | WinTabEvent::AirTap { .. }
+            | WinTabEvent::SystemGesture { .. } => EventMask::GESTURES,
+            WinTabEvent::Warning(_)
+            | WinTabEvent::TabletChanged
+            | WinTabEvent::Error(_)
+            | WinTabEvent::Ready
+            | WinTabEvent::MappingChanged => EventMask::STATUS,
+        }
     }
+}
 
-    fn StylusDown(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        _: *const StylusInfo,
-        _: u32,
-        _: *const i32,
-        _: *mut *mut i32,
-    ) -> Result<()> {
-        // checking that the stylus receiving events is the same stylus the tablet is pointing to
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        self.0.as_ref().handle_event(WinTabEvent::StylusActive)
-    }
+// `HRESULT` doesn't implement serde's traits, so it's serialised as the raw code
+#[cfg(feature = "serde")]
+mod hresult {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use windows::core::HRESULT;
 
-    fn StylusUp(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        _: *const StylusInfo,
-        _: u32,
-        _: *const i32,
-        _: *mut *mut i32,
-    ) -> Result<()> {
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        self.0.as_ref().handle_event(WinTabEvent::StylusInactive)
+    pub fn serialize<S: Serializer>(hr: &HRESULT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(hr.0)
     }
 
-    // TODO: test with more tablets - with my tablet, the GUID for the button doesnt seem to be any real, registered COM class.
-    fn StylusButtonDown(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        _: u32,
-        _: *const GUID, // what is this?
-        pstyluspos: *mut POINT,
-    ) -> Result<()> {
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        let point = unsafe { &*pstyluspos };
-
-        self.0
-            .as_ref()
-            .handle_event(WinTabEvent::StylusButtonDown(point.x, point.y))
-    }
-
-    fn StylusButtonUp(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        _: u32,
-        pguidstylusbutton: *const GUID,
-        pstyluspos: *mut POINT,
-    ) -> Result<()> {
-        debug_assert!(pirtssrc.as_ref().unwrap() == &self.0.as_ref().stylus);
-
-        let point = unsafe { &*pstyluspos };
-
-        self.0
-            .as_ref()
-            .handle_event(WinTabEvent::StylusButtonUp(point.x, point.y))
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HRESULT, D::Error> {
+        i32::deserialize(deserializer).map(HRESULT)
     }
+}
 
-    fn InAirPackets(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        pstylusinfo: *const StylusInfo,
-        cpktcount: u32,
-        cpktbufflength: u32,
-        ppackets: *const i32,
-        pcinoutpkts: *mut u32,
-        ppinoutpkts: *mut *mut i32,
-    ) -> Result<()> {
-        //println!("InAirPackets");
-        Ok(())
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    use windows::Win32::Foundation::E_FAIL;
+
+    use super::*;
+
+    #[test]
+    fn events_are_masked_by_kind() {
+        let events = [
+            WinTabEvent::StylusActive,
+            WinTabEvent::StylusMove(StylusSample::default()),
+            WinTabEvent::HoverMove(StylusSample::default()),
+            WinTabEvent::StylusButtonDown(0, 0),
+            WinTabEvent::MaxPressure,
+            WinTabEvent::EnterWindow,
+            WinTabEvent::Ready,
+            WinTabEvent::StylusInactive,
+        ];
+        let mask = EventMask::CONTACT | EventMask::MOVE | EventMask::STATUS;
+
+        let wanted: Vec<_> = events
+            .iter()
+            .filter(|event| mask.intersects(event.mask()))
+            .map(|event| format!("{:?}", event))
+            .collect();
+
+        assert_eq!(
+            wanted,
+            [
+                "StylusActive".to_owned(),
+                format!("{:?}", WinTabEvent::StylusMove(StylusSample::default())),
+                "Ready".to_owned(),
+                "StylusInactive".to_owned(),
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|event| EventMask::all().contains(event.mask())));
     }
 
-    fn Packets(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        pstylusinfo: *const StylusInfo,
-        cpktcount: u32,
-        cpktbufflength: u32,
-        ppackets: *const i32,
-        pcinoutpkts: *mut u32,
-        ppinoutpkts: *mut *mut i32,
-    ) -> Result<()> {
-        println!("Packets");
-        Ok(())
+    #[cfg(feature = "backend-rts")]
+    #[test]
+    fn tips_are_named_by_the_cursor() {
+        assert_eq!(TipType::from_cursor("Pressure Stylus", false), TipType::Pen);
+        assert_eq!(TipType::from_cursor("Grip Pen", false), TipType::Pen);
+        assert_eq!(TipType::from_cursor("Eraser", false), TipType::Eraser);
+        assert_eq!(TipType::from_cursor("Art Marker", false), TipType::Marker);
+        assert_eq!(TipType::from_cursor("Airbrush", false), TipType::Airbrush);
+        assert_eq!(TipType::from_cursor("Paint BRUSH", false), TipType::Brush);
+        assert_eq!(TipType::from_cursor("Puck", false), TipType::Unknown);
+        assert_eq!(TipType::from_cursor("", false), TipType::Unknown);
     }
 
-    fn CustomStylusDataAdded(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        pguidid: *const GUID,
-        cbdata: u32,
-        pbdata: *const u8,
-    ) -> Result<()> {
-        println!("CustomStylusDataAdded");
-        Ok(())
+    #[cfg(feature = "backend-rts")]
+    #[test]
+    fn inverted_cursors_are_erasers() {
+        assert_eq!(TipType::from_cursor("Grip Pen", true), TipType::Eraser);
+        assert_eq!(TipType::from_cursor("Airbrush", true), TipType::Eraser);
+        assert_eq!(TipType::from_cursor("Puck", true), TipType::Eraser);
     }
 
-    fn SystemEvent(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        tcid: u32,
-        sid: u32,
-        event: u16,
-        eventdata: &SYSTEM_EVENT_DATA,
-    ) -> Result<()> {
-        println!("SystemEvent");
-        Ok(())
-    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn samples_round_trip_without_their_instant() {
+        let sample = StylusSample {
+            x: 120,
+            y: -40,
+            z: Some(3),
+            pressure: 0.5,
+            tilt: Some((10.0, -20.0)),
+            cursor: 2,
+            input_source: InputSource::Touch,
+            instant: Some(Instant::now()),
+            os_time: Some(1234),
+            ..Default::default()
+        };
 
-    fn TabletAdded(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        pitablet: &Option<IInkTablet>,
-    ) -> Result<()> {
-        println!("TabletAdded");
-        Ok(())
-    }
+        let json = serde_json::to_string(&sample).unwrap();
+        let read: StylusSample = serde_json::from_str(&json).unwrap();
 
-    fn TabletRemoved(&self, pirtssrc: &Option<IRealTimeStylus>, itabletindex: i32) -> Result<()> {
-        println!("TabletRemoved");
-        Ok(())
+        assert!(!json.contains("instant"));
+        assert_eq!(
+            read,
+            StylusSample {
+                instant: None,
+                ..sample
+            }
+        );
     }
 
-    fn Error(
-        &self,
-        pirtssrc: &Option<IRealTimeStylus>,
-        piplugin: &Option<IStylusPlugin>,
-        datainterest: RealTimeStylusDataInterest,
-        hrerrorcode: HRESULT,
-        lptrkey: *mut isize,
-    ) -> Result<()> {
-        println!("Error");
-        Ok(())
-    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn events_round_trip() {
+        let round_trip = |event: WinTabEvent| -> WinTabEvent {
+            serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap()
+        };
 
-    fn UpdateMapping(&self, pirtssrc: &Option<IRealTimeStylus>) -> Result<()> {
-        println!("UpdateMapping");
-        Ok(())
+        assert!(matches!(
+            round_trip(WinTabEvent::ButtonChanged {
+                index: 1,
+                pressed: true,
+                x: 5,
+                y: 6
+            }),
+            WinTabEvent::ButtonChanged {
+                index: 1,
+                pressed: true,
+                x: 5,
+                y: 6
+            }
+        ));
+        assert!(matches!(
+            round_trip(WinTabEvent::PressureAbove(0.75)),
+            WinTabEvent::PressureAbove(p) if p == 0.75
+        ));
+        assert!(matches!(
+            round_trip(WinTabEvent::HoverMove(StylusSample {
+                x: 7,
+                ..Default::default()
+            })),
+            WinTabEvent::HoverMove(StylusSample { x: 7, .. })
+        ));
     }
 
-    fn DataInterest(&self) -> Result<RealTimeStylusDataInterest> {
-        // collect all data on stylus events
-        Ok(RTSDI_AllData)
+    #[cfg(feature = "serde")]
+    #[test]
+    fn errors_are_serialised_as_their_code() {
+        let error = WinTabEvent::Error(E_FAIL);
+        let json = serde_json::to_string(&error).unwrap();
+
+        assert_eq!(json, format!(r#"{{"Error":{}}}"#, E_FAIL.0));
+        assert!(matches!(
+            serde_json::from_str(&json).unwrap(),
+            WinTabEvent::Error(hr) if hr == E_FAIL
+        ));
     }
 }
-
-// `IStylusAsyncPlugin_Impl` is created by the `implement` macro
-impl IStylusAsyncPlugin_Impl for AsyncStylusHandler {}
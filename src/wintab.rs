@@ -0,0 +1,465 @@
+// the legacy WinTab backend, for tablets (mostly older Wacom ones) whose drivers work better with it than RealTimeStylus
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Instant;
+
+use windows::core::{s, w};
+use windows::Win32::Foundation::{BOOL, HANDLE_PTR, HINSTANCE, HWND};
+use windows::Win32::System::LibraryLoader::{FreeLibrary, GetProcAddress, LoadLibraryW};
+
+use crate::win32::{InputSource, StylusSample, WinTabEvent};
+use crate::{EasyTabError, EasyTabOptions, EasyTabResult, ListenerId, TabletBackend};
+
+// a handle to a WinTab context
+#[allow(clippy::upper_case_acronyms)]
+type HCTX = isize;
+
+type WTInfoW = unsafe extern "system" fn(u32, u32, *mut std::ffi::c_void) -> u32;
+type WTOpenW = unsafe extern "system" fn(HWND, *mut LOGCONTEXTW, BOOL) -> HCTX;
+type WTClose = unsafe extern "system" fn(HCTX) -> BOOL;
+type WTEnable = unsafe extern "system" fn(HCTX, BOOL) -> BOOL;
+type WTPacketsGet = unsafe extern "system" fn(HCTX, i32, *mut std::ffi::c_void) -> i32;
+type WTQueueSizeSet = unsafe extern "system" fn(HCTX, i32) -> BOOL;
+
+// a listener and its id. the callback is shared so the listeners can be copied out before calling them.
+type Listener = (ListenerId, Rc<dyn Fn(WinTabEvent)>);
+
+// `WTInfoW` categories and indices
+const WTI_DEFCONTEXT: u32 = 3;
+const WTI_DEVICES: u32 = 100;
+const DVC_Z: u32 = 14;
+const DVC_NPRESSURE: u32 = 15;
+
+// `LOGCONTEXTW::lcOptions` bits
+const CXO_MESSAGES: u32 = 0x0004;
+
+// packet fields. the fields of `PACKET` have to be in the same order as these bits.
+const PK_X: u32 = 0x0080;
+const PK_Y: u32 = 0x0100;
+const PK_Z: u32 = 0x0200;
+const PK_NORMAL_PRESSURE: u32 = 0x0400;
+const PK_ORIENTATION: u32 = 0x1000;
+
+const PACKETDATA: u32 = PK_X | PK_Y | PK_Z | PK_NORMAL_PRESSURE | PK_ORIENTATION;
+
+// the number of packets read from the context at a time
+const PACKET_BATCH: usize = 32;
+
+// the number of packets the context queues between polls. the default of 8 overflows within a frame on most tablets,
+// so the largest of these that the driver accepts is used.
+const QUEUE_SIZES: &[i32] = &[256, 128, 64, 32];
+
+// the structs below mirror the ones in wintab.h, so not every field is read
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(non_snake_case, dead_code, clippy::upper_case_acronyms)]
+struct LOGCONTEXTW {
+    lcName: [u16; 40],
+    lcOptions: u32,
+    lcStatus: u32,
+    lcLocks: u32,
+    lcMsgBase: u32,
+    lcDevice: u32,
+    lcPktRate: u32,
+    lcPktData: u32,
+    lcPktMode: u32,
+    lcMoveMask: u32,
+    lcBtnDnMask: u32,
+    lcBtnUpMask: u32,
+    lcInOrgX: i32,
+    lcInOrgY: i32,
+    lcInOrgZ: i32,
+    lcInExtX: i32,
+    lcInExtY: i32,
+    lcInExtZ: i32,
+    lcOutOrgX: i32,
+    lcOutOrgY: i32,
+    lcOutOrgZ: i32,
+    lcOutExtX: i32,
+    lcOutExtY: i32,
+    lcOutExtZ: i32,
+    lcSensX: u32,
+    lcSensY: u32,
+    lcSensZ: u32,
+    lcSysMode: BOOL,
+    lcSysOrgX: i32,
+    lcSysOrgY: i32,
+    lcSysExtX: i32,
+    lcSysExtY: i32,
+    lcSysSensX: u32,
+    lcSysSensY: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[allow(non_snake_case, dead_code, clippy::upper_case_acronyms)]
+struct AXIS {
+    axMin: i32,
+    axMax: i32,
+    axUnits: u32,
+    axResolution: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+struct ORIENTATION {
+    // every angle is in tenths of a degree
+    orAzimuth: i32,
+    orAltitude: i32,
+    orTwist: i32,
+}
+
+// a packet with the fields in `PACKETDATA`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+struct PACKET {
+    pkX: i32,
+    pkY: i32,
+    pkZ: i32,
+    pkNormalPressure: u32,
+    pkOrientation: ORIENTATION,
+}
+
+// the functions loaded from wintab32.dll
+struct WinTabLib {
+    module: HINSTANCE,
+    info: WTInfoW,
+    open: WTOpenW,
+    close: WTClose,
+    enable: WTEnable,
+    packets_get: WTPacketsGet,
+    queue_size_set: WTQueueSizeSet,
+}
+
+impl WinTabLib {
+    fn load() -> EasyTabResult<Self> {
+        let module = unsafe {
+            LoadLibraryW(w!("wintab32.dll"))
+                .map_err(|_| EasyTabError::WinTab("wintab32.dll could not be loaded"))?
+        };
+
+        macro_rules! load {
+            ($name:literal as $ty:ty) => {
+                match unsafe { GetProcAddress(module, s!($name)) } {
+                    Some(f) => unsafe {
+                        std::mem::transmute::<unsafe extern "system" fn() -> isize, $ty>(f)
+                    },
+                    None => {
+                        unsafe { FreeLibrary(module) };
+                        return Err(EasyTabError::WinTab(concat!(
+                            "wintab32.dll doesn't export ",
+                            $name
+                        )));
+                    }
+                }
+            };
+        }
+
+        Ok(Self {
+            info: load!("WTInfoW" as WTInfoW),
+            open: load!("WTOpenW" as WTOpenW),
+            close: load!("WTClose" as WTClose),
+            enable: load!("WTEnable" as WTEnable),
+            packets_get: load!("WTPacketsGet" as WTPacketsGet),
+            queue_size_set: load!("WTQueueSizeSet" as WTQueueSizeSet),
+            module,
+        })
+    }
+
+    // reads an axis of the default device, or `None` if the device doesn't have it
+    fn device_axis(&self, index: u32) -> Option<AXIS> {
+        let mut axis = AXIS::default();
+        let size = unsafe { (self.info)(WTI_DEVICES, index, &mut axis as *mut _ as *mut _) };
+
+        (size != 0).then_some(axis)
+    }
+}
+
+impl Drop for WinTabLib {
+    fn drop(&mut self) {
+        unsafe { FreeLibrary(self.module) };
+    }
+}
+
+// converts a packet into a sample, normalising the pressure against the range of the pressure axis
+fn decode(packet: &PACKET, pressure: &AXIS, has_z: bool) -> StylusSample {
+    let range = pressure.axMax - pressure.axMin;
+    let orientation = packet.pkOrientation;
+
+    StylusSample {
+        x: packet.pkX,
+        y: packet.pkY,
+        z: has_z.then_some(packet.pkZ),
+        pressure: if range > 0 {
+            (packet.pkNormalPressure as i32 - pressure.axMin) as f32 / range as f32
+        } else {
+            0.0
+        },
+        tilt: None,
+        azimuth: Some(orientation.orAzimuth as f32 / 10.0),
+        // the altitude is negative for the inverted end of the pen
+        altitude: Some(orientation.orAltitude.abs() as f32 / 10.0),
+        pitch: None,
+        roll: None,
+        twist: Some(orientation.orTwist as f32 / 10.0),
+        yaw: None,
+        serial: None,
+        status: None,
+        contact_size: None,
+        cursor: 0,
+        input_source: InputSource::Pen,
+        instant: None,
+        os_time: None,
+    }
+}
+
+/// A tablet read through the legacy WinTab API (`wintab32.dll`), which some older tablets and drivers only work
+/// properly with.
+///
+/// Unlike [`EasyTablet`](crate::EasyTablet), packets aren't delivered by the system, so [`poll`](Self::poll) has to be
+/// called regularly (e.g. once per frame) to receive events. The context posts a `WT_PACKET` message to the bound
+/// window as packets arrive, so polling whenever the message loop wakes up is enough.
+pub struct WinTabTablet {
+    lib: WinTabLib,
+    context: HCTX,
+    // the range of the pressure axis, used to normalise it
+    pressure: AXIS,
+    has_z: bool,
+    // only the filters read the options so far
+    #[cfg_attr(not(feature = "filters"), allow(dead_code))]
+    opts: EasyTabOptions,
+
+    active: Cell<bool>,
+    listeners: RefCell<Vec<Listener>>,
+    next_listener: Cell<usize>,
+}
+
+impl WinTabTablet {
+    /// Opens a WinTab context bound to the given window. The context starts disabled.
+    pub fn init_options(hwnd: HANDLE_PTR, opts: EasyTabOptions) -> EasyTabResult<Self> {
+        let lib = WinTabLib::load()?;
+
+        // start from the default digitising context, which reports tablet coordinates without moving the cursor
+        let mut context: LOGCONTEXTW = unsafe { std::mem::zeroed() };
+        let size = unsafe { (lib.info)(WTI_DEFCONTEXT, 0, &mut context as *mut _ as *mut _) };
+
+        if size == 0 {
+            return Err(EasyTabError::WinTab("no tablet is connected"));
+        }
+
+        context.lcOptions |= CXO_MESSAGES;
+        context.lcPktData = PACKETDATA;
+        context.lcPktMode = 0;
+        context.lcMoveMask = PACKETDATA;
+        context.lcBtnUpMask = context.lcBtnDnMask;
+        // wintab's origin is the bottom left, so flip the y axis to grow downwards like RealTimeStylus
+        context.lcOutExtY = -context.lcOutExtY;
+
+        let handle = unsafe { (lib.open)(HWND(hwnd.0 as isize), &mut context, false.into()) };
+
+        if handle == 0 {
+            return Err(EasyTabError::WinTab(
+                "the tablet context could not be opened",
+            ));
+        }
+
+        // a failed attempt leaves the default queue in place, so it's fine to carry on with it
+        for &size in QUEUE_SIZES {
+            if unsafe { (lib.queue_size_set)(handle, size) }.as_bool() {
+                break;
+            }
+        }
+
+        Ok(Self {
+            pressure: lib.device_axis(DVC_NPRESSURE).unwrap_or_default(),
+            has_z: lib.device_axis(DVC_Z).is_some(),
+            lib,
+            context: handle,
+            opts,
+
+            active: Cell::default(),
+            listeners: RefCell::default(),
+            next_listener: Cell::default(),
+        })
+    }
+
+    /// Reads every packet queued since the last call, calling the listeners for each. Returns the number of packets
+    /// read.
+    pub fn poll(&self) -> usize {
+        let mut packets = [PACKET::default(); PACKET_BATCH];
+        let mut total = 0;
+
+        loop {
+            let count = unsafe {
+                (self.lib.packets_get)(
+                    self.context,
+                    PACKET_BATCH as i32,
+                    packets.as_mut_ptr() as *mut _,
+                )
+            }
+            .max(0) as usize;
+
+            for packet in &packets[..count] {
+                self.handle_packet(packet);
+            }
+
+            total += count;
+
+            if count < PACKET_BATCH {
+                return total;
+            }
+        }
+    }
+
+    // decodes a packet, emitting the events for it
+    fn handle_packet(&self, packet: &PACKET) {
+        #[allow(unused_mut)]
+        let mut sample = StylusSample {
+            instant: Some(Instant::now()),
+            ..decode(packet, &self.pressure, self.has_z)
+        };
+
+        #[cfg(feature = "filters")]
+        if sample.pressure < self.opts.pressure_deadzone {
+            sample.pressure = 0.0;
+        }
+
+        let down = sample.pressure > 0.0;
+
+        if down != self.active.replace(down) {
+            self.emit(if down {
+                WinTabEvent::StylusActive
+            } else {
+                WinTabEvent::StylusInactive
+            });
+        }
+
+        // the stylus only counts as down while it has pressure, so there are no zero pressure moves for
+        // `require_pressure_for_move` to drop
+        self.emit(if down {
+            WinTabEvent::StylusMove(sample)
+        } else {
+            WinTabEvent::HoverMove(sample)
+        });
+    }
+
+    fn emit(&self, event: WinTabEvent) {
+        // copied out, as the listeners may add or remove listeners
+        let listeners = self.listeners.borrow().clone();
+
+        for (id, listener) in listeners.iter() {
+            if self.listeners.borrow().iter().any(|(lid, _)| lid == id) {
+                listener(event);
+            }
+        }
+    }
+}
+
+impl TabletBackend for WinTabTablet {
+    fn open(hwnd: usize, opts: EasyTabOptions) -> EasyTabResult<Self> {
+        WinTabTablet::init_options(HANDLE_PTR(hwnd), opts)
+    }
+
+    fn enable(&self) -> EasyTabResult<()> {
+        if !unsafe { (self.lib.enable)(self.context, true.into()) }.as_bool() {
+            return Err(EasyTabError::WinTab(
+                "the tablet context could not be enabled",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn disable(&self) -> EasyTabResult<()> {
+        if !unsafe { (self.lib.enable)(self.context, false.into()) }.as_bool() {
+            return Err(EasyTabError::WinTab(
+                "the tablet context could not be disabled",
+            ));
+        }
+
+        if self.active.replace(false) {
+            self.emit(WinTabEvent::StylusInactive);
+        }
+
+        Ok(())
+    }
+
+    fn add_listener(&self, cb: Box<dyn Fn(WinTabEvent)>) -> ListenerId {
+        let id = ListenerId(self.next_listener.get());
+        self.next_listener.set(id.0 + 1);
+
+        self.listeners.borrow_mut().push((id, cb.into()));
+
+        id
+    }
+
+    fn remove_listener(&self, id: ListenerId) -> bool {
+        let mut listeners = self.listeners.borrow_mut();
+        let len = listeners.len();
+
+        listeners.retain(|(lid, _)| *lid != id);
+
+        listeners.len() != len
+    }
+
+    fn poll(&self) -> usize {
+        WinTabTablet::poll(self)
+    }
+}
+
+impl Drop for WinTabTablet {
+    fn drop(&mut self) {
+        unsafe { (self.lib.close)(self.context) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_a_packet() {
+        let packet = PACKET {
+            pkX: 100,
+            pkY: -50,
+            pkZ: 3,
+            pkNormalPressure: 612,
+            pkOrientation: ORIENTATION {
+                orAzimuth: 900,
+                orAltitude: -450,
+                orTwist: 1800,
+            },
+        };
+        let pressure = AXIS {
+            axMin: 100,
+            axMax: 1124,
+            ..Default::default()
+        };
+
+        let sample = decode(&packet, &pressure, true);
+
+        assert_eq!((sample.x, sample.y, sample.z), (100, -50, Some(3)));
+        assert_eq!(sample.pressure, 0.5);
+        assert_eq!(sample.azimuth, Some(90.0));
+        assert_eq!(sample.altitude, Some(45.0));
+        assert_eq!(sample.twist, Some(180.0));
+    }
+
+    #[test]
+    fn missing_axes_decode_to_nothing() {
+        let packet = PACKET {
+            pkZ: 3,
+            pkNormalPressure: 500,
+            ..Default::default()
+        };
+
+        let sample = decode(&packet, &AXIS::default(), false);
+
+        assert_eq!(sample.z, None);
+        assert_eq!(sample.pressure, 0.0);
+    }
+}